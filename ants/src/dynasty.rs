@@ -24,12 +24,11 @@ pub struct DynastyAgent {
     // ant still can carry more food.
     pub state_values_without_food: State,
     // Roll the dice for exploratory moves.
-    rng: ThreadRng,
+    rng: SeededRng,
 }
 
 impl DynastyAgent {
-    pub fn new(dynasty_id: DynastyId, size: usize) -> Self {
-        let mut rng = ThreadRng::default();
+    pub fn new(dynasty_id: DynastyId, size: usize, mut rng: SeededRng) -> Self {
         let mut empty_state_values = || {
             (0..size)
                 .map(|_| (0..size).map(|_| rng.gen_range(0.4, 0.6)).collect())
@@ -86,8 +85,7 @@ impl DynastyAgent {
 
         // Finds the best action to take.
         for (direction, (x, y)) in actions {
-            if let Some(action_value) =
-                get_state_value_at(*x, *y, &state_values)
+            if let Some(action_value) = get_state_value_at(*x, *y, state_values)
             {
                 if best_action.is_none() || action_value > best_action_value {
                     best_action_value = action_value;
@@ -113,7 +111,7 @@ fn get_state_value_at(x: isize, y: isize, state: &State) -> Option<f32> {
     state
         .get(y as usize)
         .and_then(|row| row.get(x as usize))
-        .map(|x| *x)
+        .copied()
 }
 
 impl From<Reward> for f32 {
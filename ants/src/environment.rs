@@ -65,15 +65,13 @@ pub struct Environment {
     /// Time counter,
     pub steps: usize,
     // Cache randomness generator.
-    rng: ThreadRng,
+    rng: SeededRng,
     // Caching memory so that it can be reused between steps.
     ant_moves: Vec<AntMove>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Dynasty {
-    /// The same as the index in the array.
-    pub id: DynastyId,
     /// Counter for how much food has the dynasty gathered.
     pub food: FoodUnit,
     /// How much ants does a dynasty have alive. If this gets to 0, dynasty is
@@ -105,10 +103,10 @@ pub struct Ant {
     pub reward: Reward,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum Cell {
+    #[default]
     Grass,
-    Wall,
     // TODO: Figure out a way to use `Ant` struct.
     Ant {
         dynasty_id: DynastyId,
@@ -149,19 +147,13 @@ struct AntMove {
 
 //------------------------------- Support impl -------------------------------//
 
-impl Default for Cell {
-    fn default() -> Self {
-        Self::Grass
-    }
-}
-
 impl Environment {
-    pub fn new(size: usize, dynasties: u8) -> Self {
+    pub fn new(size: usize, dynasties: u8, rng: SeededRng) -> Self {
         assert!(dynasties > 1);
         assert!(size > dynasties as usize * 5);
 
         let dynasties: Vec<Dynasty> =
-            (0..dynasties).map(Dynasty::new).collect();
+            (0..dynasties).map(|_| Dynasty::new()).collect();
         let mut cells: Vec<Vec<Cell>> = (0..size)
             .map(|_| (0..size).map(|_| Cell::default()).collect())
             .collect();
@@ -169,10 +161,10 @@ impl Environment {
         // Each dynasty nest is randomly positioned. We avoid edges for
         // simplification of new ant placing logic.
         // let mut rng = ThreadRng::default();
-        // for dynasty in &dynasties {
+        // for dynasty_id in 0..dynasties.len() {
         //     let nest_x = rng.gen_range(1, size - 1);
         //     let nest_y = rng.gen_range(1, size - 1);
-        //     cells[nest_y][nest_x] = Cell::Nest(dynasty.id);
+        //     cells[nest_y][nest_x] = Cell::Nest(dynasty_id as DynastyId);
         // }
 
         // For debugging make the nests deterministic.
@@ -188,7 +180,7 @@ impl Environment {
         Self {
             steps: 0,
             size,
-            rng: ThreadRng::default(),
+            rng,
             cells,
             dynasties,
             ant_moves: Vec::new(),
@@ -205,8 +197,8 @@ impl Environment {
             .filter(|d| !d.is_dead())
             .count();
 
-        return dynasties_alive < 2
-            || self.steps > MAX_ENVIRONMENT_AGE.unwrap_or(usize::max_value());
+        dynasties_alive < 2
+            || self.steps > MAX_ENVIRONMENT_AGE.unwrap_or(usize::MAX)
     }
 
     // Each axis looks like this, when they're added, the result looks like 4 hills
@@ -238,9 +230,8 @@ impl Environment {
 }
 
 impl Dynasty {
-    pub fn new(id: DynastyId) -> Self {
+    pub fn new() -> Self {
         Self {
-            id,
             food: ANT_SPAWN_COST + INITIAL_DYNASTY_EXTRA_FOOD,
             ants: 0,
         }
@@ -253,10 +244,7 @@ impl Dynasty {
 
 impl Cell {
     fn is_grass(&self) -> bool {
-        match self {
-            Self::Grass => true,
-            _ => false,
-        }
+        matches!(self, Self::Grass)
     }
 
     fn ant(dynasty_id: DynastyId) -> Self {
@@ -291,7 +279,7 @@ impl From<Ant> for Cell {
 }
 
 impl Direction {
-    pub fn rand(rng: &mut ThreadRng) -> Self {
+    pub fn rand(rng: &mut SeededRng) -> Self {
         match rng.gen_range(0, 4) {
             0 => Self::North,
             1 => Self::East,
@@ -445,7 +433,6 @@ impl Environment {
                     })
                 }
             }
-            Cell::Wall => None,
         }
     }
 
@@ -557,7 +544,6 @@ impl Environment {
                     }
                     self.cells[y][x] = ant.into();
                 }
-                Cell::Wall => self.cells[y][x] = ant.into(),
             }
         }
     }
@@ -566,29 +552,27 @@ impl Environment {
     pub fn reward_winner(self, dynasty_agents: &mut [DynastyAgent]) {
         for (y, row) in self.cells.into_iter().enumerate() {
             for (x, cell) in row.into_iter().enumerate() {
-                match cell {
-                    Cell::Ant {
-                        dynasty_id,
-                        carries_food,
-                        direction,
-                        ttl,
-                        ..
-                    } => {
-                        let dynasty_agent =
-                            &mut dynasty_agents[dynasty_id as usize];
-                        dynasty_agent.pick_action(
-                            x,
-                            y,
-                            Ant {
-                                dynasty_id,
-                                carries_food,
-                                reward: Reward::Survivor,
-                                direction,
-                                ttl,
-                            },
-                        );
-                    }
-                    _ => (),
+                if let Cell::Ant {
+                    dynasty_id,
+                    carries_food,
+                    direction,
+                    ttl,
+                    ..
+                } = cell
+                {
+                    let dynasty_agent =
+                        &mut dynasty_agents[dynasty_id as usize];
+                    dynasty_agent.pick_action(
+                        x,
+                        y,
+                        Ant {
+                            dynasty_id,
+                            carries_food,
+                            reward: Reward::Survivor,
+                            direction,
+                            ttl,
+                        },
+                    );
                 }
             }
         }
@@ -4,8 +4,12 @@ mod ext;
 
 use dynasty::DynastyAgent;
 use environment::Environment;
+use ext::SeededRng;
+use rand::prelude::*;
+use std::env;
 use std::fs::File;
 use std::io::Write;
+use std::process;
 
 // How many cells in a row and in a column.
 const GRID_SIZE: usize = 200;
@@ -15,14 +19,38 @@ const DYNASTIES: u8 = 5;
 
 const SIMULATED_ENVS: usize = 11;
 
+const USAGE: &str = "Usage: ants [--seed <number>]";
+
 fn main() {
+    // The same seed replays the same simulation. Without one, a seed is picked
+    // at random and printed so that the run can be replayed.
+    let seed = match parse_seed(env::args().skip(1)) {
+        Ok(seed) => seed.unwrap_or_else(|| thread_rng().gen()),
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    println!("seed: {}", seed);
+
+    // Each agent and each environment draws from its own generator, seeded
+    // from this one.
+    let mut rng = SeededRng::seed_from_u64(seed);
+
     let mut stats = File::create("debug/data.txt").unwrap();
     let mut dynasty_agents: Vec<_> = (0..DYNASTIES)
-        .map(|id| DynastyAgent::new(id, GRID_SIZE))
+        .map(|id| {
+            DynastyAgent::new(
+                id,
+                GRID_SIZE,
+                SeededRng::from_rng(&mut rng).unwrap(),
+            )
+        })
         .collect();
 
     for env_n in 0..SIMULATED_ENVS {
-        let mut environment = Environment::new(GRID_SIZE, DYNASTIES);
+        let env_rng = SeededRng::from_rng(&mut rng).unwrap();
+        let mut environment = Environment::new(GRID_SIZE, DYNASTIES, env_rng);
         loop {
             environment.step(&mut dynasty_agents);
 
@@ -44,6 +72,25 @@ fn main() {
     }
 }
 
+/// The seed given by the `--seed` argument, if any.
+fn parse_seed(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<u64>, String> {
+    let mut seed = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or("Missing a value for --seed")?;
+                seed = Some(value.parse().map_err(|_| {
+                    format!("Seed must be a number, got '{}'", value)
+                })?);
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+    Ok(seed)
+}
+
 // TODO: For debug now.
 // https://color.adobe.com/create
 // https://www.rapidtables.com/convert/color/hex-to-rgb.html
@@ -79,7 +126,6 @@ fn render(env: usize, environment: &Environment) {
         for (x, cell) in row.iter().enumerate() {
             let pixel = image_view.get_pixel_mut(x as u32, y as u32);
             match cell {
-                Cell::Wall => pixel.0 = [0, 0, 0],
                 Cell::Grass => pixel.0 = [255, 255, 255],
                 Cell::Trail { dynasty_id, .. } => {
                     pixel.0 = DYN_TRAIL_COLOURS[*dynasty_id as usize]
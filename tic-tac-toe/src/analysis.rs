@@ -0,0 +1,227 @@
+//! Shows why the agent moves the way it does. For each empty field, the
+//! analysis lists the learned value of the grid after marking it, and what
+//! marking it leads to under perfect play.
+
+use super::minimax::Minimax;
+use super::{field_name, Field, Grid, Player, ValueFunction};
+use std::cmp::Ordering;
+
+/// Prints the analysis of the grid in which the player is to move. The values
+/// were learned for given player, the other player's grids are looked up with
+/// the marks swapped. Without values, only the minimax results are shown.
+pub fn analyze(
+    grid: Grid,
+    player: Player,
+    values: Option<(&dyn ValueFunction, Player)>,
+    minimax: &mut Minimax,
+) {
+    // Like the agent does, sees the grid as the player the values are of, so
+    // that the mover's marks are always theirs.
+    let seen = values.map(|(values, values_player)| {
+        if player == values_player {
+            (values, grid, values_player)
+        } else {
+            (values, grid.swap_players(), values_player)
+        }
+    });
+    let value_of = |field: usize| {
+        seen.map(|(values, grid, player)| values.value(grid.put(field, player)))
+    };
+
+    let actions = grid.empty_fields();
+    let choice = seen.map(|(values, grid, player)| {
+        values.best_field(grid, player, &actions)
+    });
+    let scores: Vec<_> = actions
+        .iter()
+        .map(|field| minimax.score_action(grid, player, *field))
+        .collect();
+    let best_score = *scores.iter().max().expect("The game must not be over");
+
+    println!("{:?} to move.", player);
+    println!("field    value  perfect play");
+    for (field, score) in actions.iter().zip(&scores) {
+        let value = match value_of(*field) {
+            Some(value) => format!("{:.3}", value),
+            None => "-".to_string(),
+        };
+        let mut notes = Vec::new();
+        if Some(*field) == choice {
+            notes.push("agent");
+        }
+        if *score == best_score {
+            notes.push("best");
+        }
+        let line = format!(
+            "{} ({})  {:>6}  {:<12}  {}",
+            field + 1,
+            field_name(*field),
+            value,
+            result(*score),
+            notes.join(", ")
+        );
+        println!("{}", line.trim_end());
+    }
+
+    if let Some(choice) = choice {
+        let score = scores[actions.iter().position(|a| *a == choice).unwrap()];
+        if score == best_score {
+            println!(
+                "The agent would play {} ({}), one of the best moves, which \
+                 leads to {} under perfect play.",
+                choice + 1,
+                field_name(choice),
+                result(score)
+            );
+        } else {
+            println!(
+                "The agent would play {} ({}), a mistake which leads to {} \
+                 under perfect play, while the best moves lead to {}.",
+                choice + 1,
+                field_name(choice),
+                result(score),
+                result(best_score)
+            );
+        }
+    }
+}
+
+/// Describes the minimax score of a move.
+pub(crate) fn result(score: i8) -> &'static str {
+    match score.cmp(&0) {
+        Ordering::Greater => "a win",
+        Ordering::Equal => "a draw",
+        Ordering::Less => "a loss",
+    }
+}
+
+/// Writes the grid row by row in the format `parse_board` reads, e.g.
+/// `x.o/.x./..o`.
+pub fn board(grid: Grid) -> String {
+    let rows: Vec<String> = grid
+        .fields()
+        .chunks(3)
+        .map(|row| {
+            row.iter()
+                .map(|field| match field {
+                    Field::X => 'x',
+                    Field::O => 'o',
+                    Field::Empty => '.',
+                })
+                .collect()
+        })
+        .collect();
+    rows.join("/")
+}
+
+/// Parses a board of 9 fields row by row, `x` and `o` for the marks and `.`,
+/// `-` or `_` for empty fields. Slashes, bars and spaces between the rows are
+/// ignored, e.g. `x.o/.x./..o`. Returns the grid and the player to move, which
+/// is the one with fewer marks, or the player who moved first if both have
+/// the same number.
+pub fn parse_board(
+    board: &str,
+    first: Player,
+) -> Result<(Grid, Player), String> {
+    let fields: Vec<_> = board
+        .chars()
+        .filter(|c| !matches!(c, '/' | '|' | ' '))
+        .map(|c| match c.to_ascii_lowercase() {
+            'x' => Ok(Field::X),
+            'o' => Ok(Field::O),
+            '.' | '-' | '_' => Ok(Field::Empty),
+            _ => Err(format!("Unknown field '{}' in board {}", c, board)),
+        })
+        .collect::<Result<_, _>>()?;
+    if fields.len() != 9 {
+        return Err(format!(
+            "Board {} has {} fields instead of 9",
+            board,
+            fields.len()
+        ));
+    }
+
+    let grid = fields
+        .iter()
+        .enumerate()
+        .fold(Grid::new(), |grid, (i, field)| grid.with_field(i, *field));
+    let count = |player: Player| {
+        fields.iter().filter(|field| **field == player).count()
+    };
+    let (first_marks, second_marks) = (count(first), count(first.opponent()));
+    let player = if first_marks == second_marks {
+        first
+    } else if first_marks == second_marks + 1 {
+        first.opponent()
+    } else {
+        return Err(format!(
+            "Board {} can't come up in a game which {:?} started",
+            board, first
+        ));
+    };
+    if grid.outcome().is_over() {
+        return Err(format!("The game on board {} is over", board));
+    }
+
+    Ok((grid, player))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_board_in_every_accepted_syntax() {
+        let expected = Grid::new()
+            .put(0, Player::X)
+            .put(2, Player::O)
+            .put(4, Player::X)
+            .put(8, Player::O);
+        for board in ["x.o/.x./..o", "X-O|-X-|--O", "x_o _x_ __o", "x.o.x...o"]
+        {
+            assert_eq!(
+                parse_board(board, Player::X),
+                Ok((expected, Player::X))
+            );
+        }
+    }
+
+    #[test]
+    fn player_to_move_depends_on_who_moved_first() {
+        let grid = Grid::new().put(4, Player::X);
+        assert_eq!(parse_board(".../.../...", Player::O).unwrap().1, Player::O);
+        assert_eq!(
+            parse_board(".../.x./...", Player::X),
+            Ok((grid, Player::O))
+        );
+        assert!(parse_board(".../.x./...", Player::O).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_number_of_fields() {
+        assert!(parse_board("x.o/.x./..", Player::X).is_err());
+        assert!(parse_board("x.o/.x./..o/.", Player::X).is_err());
+        assert!(parse_board("", Player::X).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(parse_board("x.o/.y./..o", Player::X).is_err());
+        assert!(parse_board("x.o,.x.,..o", Player::X).is_err());
+    }
+
+    #[test]
+    fn rejects_impossible_number_of_marks() {
+        assert!(parse_board("xx./.../...", Player::X).is_err());
+        assert!(parse_board("o../.../...", Player::X).is_err());
+        assert!(parse_board("xxo/.../...", Player::O).is_err());
+    }
+
+    #[test]
+    fn rejects_finished_games() {
+        // Both players have a row, only X has one and the board is full.
+        assert!(parse_board("xxx/ooo/...", Player::X).is_err());
+        assert!(parse_board("xxx/oo./...", Player::X).is_err());
+        assert!(parse_board("xox/xoo/oxx", Player::X).is_err());
+    }
+}
@@ -0,0 +1,388 @@
+//! Command line interface. The first argument is a subcommand, followed by an
+//! optional number of games and flags in any order. Without a subcommand, the
+//! agent trains and then plays against a human.
+
+use std::str::FromStr;
+use tic_tac_toe::learner::{Algorithm, Hyperparameters};
+use tic_tac_toe::mcts::{Guidance, Mcts};
+use tic_tac_toe::minimax::Minimax;
+use tic_tac_toe::policies::{Policy, Random};
+use tic_tac_toe::{Indexing, Player};
+
+/// Printed for `--help` and when the arguments can't be parsed.
+pub(super) const USAGE: &str = "\
+Usage: tic_tac_toe [COMMAND] [GAMES] [FLAGS]
+
+Commands:
+  train               Train the agent for GAMES games (1000) against the
+                      opponent and save it to the model file
+  self-play           Train the agent for GAMES games (1000) against a second
+                      learner and save it to the model file
+  play                Load the agent from the model file, or train it for
+                      GAMES games (1000), and play against a human (default)
+  eval                Load the agent from the model file, or train it for
+                      GAMES games (1000), and evaluate it against the opponent
+  analyze <BOARD>     Load the agent from the model file, or train it for
+                      GAMES games (1000), and show how it values each move on
+                      the board, given row by row such as x.o/.x./..o
+  report              Load the agent from the model file, or train it for
+                      GAMES games (1000), and compare its values with the
+                      exact values of every grid it can move into
+  tournament <ENTRANT>...
+                      Play every pair of the entrants for GAMES games (100)
+                      on each side and rate them. An entrant is random,
+                      minimax, mcts or a model file played greedily
+  replay <FILE>       Show each move of the recorded games with the agent's
+                      values, if a model file is given
+  export              Convert the model file into the output file
+  compare-symmetry    Compare learning with and without symmetries
+  compare-algorithms  Compare how the algorithms learn
+
+Flags:
+  --model <FILE>            Where the agent is saved to by train and self-play
+                            and loaded from by play, eval and export
+  --from <FILE>             Values which train and self-play continue from
+  --second-model <FILE>     Where self-play saves the second learner
+  --output <FILE>           Where export writes to, .txt for the text format,
+                            and where tournament and report write their CSV
+                            results
+  --opponent <POLICY>       random, minimax or mcts. Who train plays against
+                            (random) and who eval plays against (minimax)
+  --simulations <COUNT>     Simulations per move of tree search (1000)
+  --search <GUIDANCE>       Let play and eval pick the agent's moves by tree
+                            search instead of by its values alone. none
+                            ignores the values, leaf estimates grids by them
+                            and rollout plays out games by them
+  --side <x|o>              The side the agent plays
+  --first <x|o>             Who moves first (x)
+  --alternate               Cycle through both sides and both move orders
+  --algorithm <ALGORITHM>   afterstate, q-learning, sarsa, mc-first-visit,
+                            mc-every-visit, linear or network (afterstate)
+  --hidden <UNITS>          Hidden units of a new network (40)
+  --step-size <ALPHA>       Step size of the updates (0.2)
+  --step-size-schedule <SCHEDULE>
+                            constant, sample-average or
+                            polynomial:<exponent> (constant)
+  --exploration <EPSILON>   Probability of an exploratory move (0.01)
+  --exploration-strategy <STRATEGY>
+                            constant, linear:<end>:<games>,
+                            exponential:<rate>, softmax:<start>:<end>:<games>
+                            or ucb:<c> (constant)
+  --lambda <LAMBDA>         Decay of eligibility traces (0)
+  --traces <TRACES>         accumulating or replacing (accumulating)
+  --symmetry                Share values between symmetrical grids
+  --reachable               Store values of reachable grids only
+  --record <FILE>           Append records of the played games to the file
+  --record-every <N>        Also record every Nth training game
+  --game <N>                The game replay shows, all of them by default
+  --seed <SEED>             Seed of the random number generator
+  --help                    Print this message
+";
+
+/// What the program does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Command {
+    Train,
+    SelfPlay,
+    Play,
+    Eval,
+    Analyze,
+    Report,
+    Tournament,
+    Replay,
+    Export,
+    CompareSymmetry,
+    CompareAlgorithms,
+}
+
+/// The policies the agent can train or be evaluated against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Opponent {
+    Random,
+    Minimax,
+    /// Tree search with random rollouts.
+    Mcts,
+}
+
+/// Command line arguments.
+#[derive(Debug)]
+pub(super) struct Args {
+    pub command: Command,
+    /// How many games should the agent play to train its values.
+    pub games: Option<u64>,
+    /// The file the agent is saved to or loaded from, depending on the
+    /// command.
+    pub model: Option<String>,
+    /// The file the training continues from.
+    pub from: Option<String>,
+    /// The file self-play saves the second learner to.
+    pub second_model: Option<String>,
+    /// The file export writes to, or tournament writes its results to.
+    pub output: Option<String>,
+    /// The board analyze shows the values of.
+    pub board: Option<String>,
+    /// The policies and model files tournament plays.
+    pub entrants: Vec<String>,
+    /// The file with the games replay shows.
+    pub replay: Option<String>,
+    /// The number of the game replay shows.
+    pub game: Option<u64>,
+    /// The file the played games are recorded into.
+    pub record: Option<String>,
+    /// Every how many training games one is recorded.
+    pub record_every: Option<u64>,
+    pub opponent: Option<Opponent>,
+    /// Which side does the agent play. Defaults to the side its values were
+    /// trained for.
+    pub side: Option<Player>,
+    /// Who moves first.
+    pub first: Option<Player>,
+    /// Train on both sides and both move orders.
+    pub alternate: bool,
+    /// Which algorithm does the agent learn by.
+    pub algorithm: Option<Algorithm>,
+    /// Overrides the step size, otherwise the one the loaded values were
+    /// trained with or the default is used.
+    pub step_size: Option<f32>,
+    /// Overrides the exploration probability, the same as the step size.
+    pub exploration_probability: Option<f32>,
+    /// Step size schedule, exploration strategy, lambda, traces and hidden
+    /// units. The step size and exploration probability are resolved once it
+    /// is known whether any values are loaded.
+    pub hyperparameters: Hyperparameters,
+    /// How the values are indexed. Loaded values are converted to it.
+    pub indexing: Option<Indexing>,
+    /// How many simulations does tree search run before each move.
+    pub simulations: u32,
+    /// Whether the agent picks its moves by tree search when it doesn't
+    /// learn, and how its values guide the search.
+    pub search: Option<Guidance>,
+    /// Seeds the random number generator. A random seed is picked if none is
+    /// given.
+    pub seed: Option<u64>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments, without the name of the program.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        let command = match args.peek().and_then(|arg| arg.parse().ok()) {
+            Some(command) => {
+                args.next();
+                command
+            }
+            None => Command::Play,
+        };
+
+        let mut parsed = Self {
+            command,
+            games: None,
+            model: None,
+            from: None,
+            second_model: None,
+            output: None,
+            board: None,
+            entrants: Vec::new(),
+            replay: None,
+            game: None,
+            record: None,
+            record_every: None,
+            opponent: None,
+            side: None,
+            first: None,
+            alternate: false,
+            algorithm: None,
+            step_size: None,
+            exploration_probability: None,
+            hyperparameters: Hyperparameters::default(),
+            indexing: None,
+            simulations: 1000,
+            search: None,
+            seed: None,
+            help: false,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing the value of {}", arg))
+            };
+            match arg.as_str() {
+                "--model" => parsed.model = Some(value()?),
+                "--from" => parsed.from = Some(value()?),
+                "--second-model" => parsed.second_model = Some(value()?),
+                "--output" => parsed.output = Some(value()?),
+                "--opponent" => parsed.opponent = Some(value()?.parse()?),
+                "--side" => parsed.side = Some(value()?.parse()?),
+                "--first" => parsed.first = Some(value()?.parse()?),
+                "--alternate" => parsed.alternate = true,
+                "--algorithm" => parsed.algorithm = Some(value()?.parse()?),
+                "--step-size" => {
+                    parsed.step_size = Some(probability(&arg, &value()?)?)
+                }
+                "--exploration" => {
+                    parsed.exploration_probability =
+                        Some(probability(&arg, &value()?)?)
+                }
+                "--step-size-schedule" => {
+                    parsed.hyperparameters.step_size_schedule =
+                        value()?.parse()?
+                }
+                "--exploration-strategy" => {
+                    parsed.hyperparameters.exploration = value()?.parse()?
+                }
+                "--lambda" => {
+                    parsed.hyperparameters.lambda =
+                        probability(&arg, &value()?)?
+                }
+                "--traces" => {
+                    parsed.hyperparameters.traces = value()?.parse()?
+                }
+                "--hidden" => {
+                    parsed.hyperparameters.hidden_units =
+                        positive(&arg, &value()?)?
+                }
+                "--symmetry" => parsed.indexing = Some(Indexing::Canonical),
+                "--reachable" => parsed.indexing = Some(Indexing::Reachable),
+                "--simulations" => {
+                    parsed.simulations = positive(&arg, &value()?)?
+                }
+                "--search" => parsed.search = Some(value()?.parse()?),
+                "--record" => parsed.record = Some(value()?),
+                "--record-every" => {
+                    parsed.record_every = Some(positive(&arg, &value()?)?)
+                }
+                "--game" => parsed.game = Some(positive(&arg, &value()?)?),
+                "--seed" => {
+                    parsed.seed = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "Seed must be a number".to_string())?,
+                    )
+                }
+                "--help" | "-h" => parsed.help = true,
+                _ if arg.starts_with('-') => {
+                    return Err(format!("Unknown flag {}", arg))
+                }
+                _ if parsed.command == Command::Analyze
+                    && arg.parse::<u64>().is_err() =>
+                {
+                    parsed.board = Some(arg)
+                }
+                _ if parsed.command == Command::Replay
+                    && parsed.replay.is_none() =>
+                {
+                    parsed.replay = Some(arg)
+                }
+                _ if parsed.command == Command::Tournament
+                    && arg.parse::<u64>().is_err() =>
+                {
+                    parsed.entrants.push(arg)
+                }
+                _ => {
+                    parsed.games = Some(arg.parse().map_err(|_| {
+                        format!(
+                            "Expected a command or a number of games, not {}",
+                            arg
+                        )
+                    })?)
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether the command draws random numbers, so that its run depends on
+    /// the seed. Export and replay never do, analyze and report only when
+    /// they train the agent first, which they do unless it is loaded.
+    pub fn uses_rng(&self) -> bool {
+        match self.command {
+            Command::Export | Command::Replay => false,
+            Command::Analyze | Command::Report => {
+                self.games.map_or(self.model.is_none(), |games| games > 0)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Parses a whole number greater than 0.
+fn positive<T: FromStr + Default + PartialOrd>(
+    flag: &str,
+    value: &str,
+) -> Result<T, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|n| *n > T::default())
+        .ok_or_else(|| format!("{} must be a positive number", flag))
+}
+
+/// Parses a number between 0 and 1.
+fn probability(flag: &str, value: &str) -> Result<f32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|p| (0.0..=1.0).contains(p))
+        .ok_or_else(|| format!("{} must be a number between 0 and 1", flag))
+}
+
+impl Opponent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Random => "random",
+            Self::Minimax => "minimax",
+            Self::Mcts => "mcts",
+        }
+    }
+
+    /// Creates the policy of the opponent. Tree search runs given number of
+    /// simulations per move.
+    pub fn policy(self, simulations: u32) -> Box<dyn Policy> {
+        match self {
+            Self::Random => Box::new(Random),
+            Self::Minimax => Box::new(Minimax::new()),
+            Self::Mcts => Box::new(Mcts::new(simulations)),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "train" => Ok(Self::Train),
+            "self-play" => Ok(Self::SelfPlay),
+            "play" => Ok(Self::Play),
+            "eval" => Ok(Self::Eval),
+            "analyze" => Ok(Self::Analyze),
+            "report" => Ok(Self::Report),
+            "tournament" => Ok(Self::Tournament),
+            "replay" => Ok(Self::Replay),
+            "export" => Ok(Self::Export),
+            "compare-symmetry" => Ok(Self::CompareSymmetry),
+            "compare-algorithms" => Ok(Self::CompareAlgorithms),
+            _ => Err(format!("Unknown command '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for Opponent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "minimax" => Ok(Self::Minimax),
+            "mcts" => Ok(Self::Mcts),
+            _ => Err(format!(
+                "Unknown opponent '{}', expected random, minimax or mcts",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Measures how well a trained agent plays, against a perfect player and
+//! against a random one.
+
+use super::minimax::Minimax;
+use super::policies::Policy;
+use super::training::Tally;
+use super::{
+    field_name, play_game, seats, GameOutcome, Grid, Player, SeededRng,
+};
+
+/// How many games are played from each opening. The minimax policy picks at
+/// random among equally good moves, hence the games differ.
+const GAMES_PER_OPENING: usize = 100;
+
+/// How many games are played against an opponent whose moves can't be
+/// enumerated, such as tree search.
+const GAMES_AGAINST_POLICY: usize = 100;
+
+/// Plays given field as the very first move of the game and then lets the
+/// inner policy take over.
+struct Opening<'a> {
+    field: usize,
+    policy: &'a mut dyn Policy,
+}
+
+impl Policy for Opening<'_> {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        if actions.len() == 9 {
+            self.field
+        } else {
+            self.policy.pick(rng, grid, player, actions)
+        }
+    }
+}
+
+/// Plays the agent against a perfect player, starting from each of the 9
+/// possible first moves of the player who moves first, whether that is the
+/// agent or its opponent. Prints how often the agent won, drew and lost from
+/// each opening. Against a perfect player, draws are the best the agent can
+/// do.
+pub fn against_minimax(
+    rng: &mut SeededRng,
+    agent: &mut dyn Policy,
+    player: Player,
+    first: Player,
+) -> Tally {
+    let mut minimax = Minimax::new();
+    let mut total = Tally::default();
+
+    println!(
+        "Evaluating {:?} against a perfect player, {:?} moves first.",
+        player, first
+    );
+    println!("opening    wins   draws  losses");
+    for field in 0..9 {
+        let mut tally = Tally::default();
+        for _ in 0..GAMES_PER_OPENING {
+            let grid = if first == player {
+                let mut opening = Opening {
+                    field,
+                    policy: agent,
+                };
+                let (x, o) = seats(player, &mut opening, &mut minimax);
+                play_game(rng, x, o, first)
+            } else {
+                let mut opening = Opening {
+                    field,
+                    policy: &mut minimax,
+                };
+                let (x, o) = seats(player, agent, &mut opening);
+                play_game(rng, x, o, first)
+            };
+            tally.record(grid);
+        }
+
+        println!(
+            "{:>7} {:>7} {:>7} {:>7}",
+            field_name(field),
+            tally.wins(player),
+            tally.draws,
+            tally.losses(player)
+        );
+        total.x_wins += tally.x_wins;
+        total.o_wins += tally.o_wins;
+        total.draws += tally.draws;
+    }
+
+    println!(
+        "{:>7} {:>7} {:>7} {:>7}",
+        "total",
+        total.wins(player),
+        total.draws,
+        total.losses(player)
+    );
+    total
+}
+
+/// Plays the agent against the opponent for `GAMES_AGAINST_POLICY` games and
+/// counts how they ended.
+pub fn against(
+    rng: &mut SeededRng,
+    agent: &mut dyn Policy,
+    opponent: &mut dyn Policy,
+    player: Player,
+    first: Player,
+) -> Tally {
+    let mut tally = Tally::default();
+    for _ in 0..GAMES_AGAINST_POLICY {
+        let (x, o) = seats(player, agent, opponent);
+        tally.record(play_game(rng, x, o, first));
+    }
+    tally
+}
+
+/// Probabilities with which a game ends, from the perspective of one player.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Odds {
+    /// The probability of a win.
+    pub win: f32,
+    /// The probability of a draw.
+    pub draw: f32,
+    /// The probability of a loss.
+    pub loss: f32,
+}
+
+/// Computes exactly how likely the agent is to win, draw and lose against a
+/// random policy when the first player moves first. Rather than sampling
+/// games, it walks the whole game tree and weighs each of the random policy's
+/// moves equally. The agent must not learn and must always pick the same
+/// action in the same grid, such as a greedy policy does.
+pub fn against_random(
+    rng: &mut SeededRng,
+    agent: &mut dyn Policy,
+    player: Player,
+    first: Player,
+) -> Odds {
+    odds_against_random(rng, agent, player, Grid::new(), first)
+}
+
+fn odds_against_random(
+    rng: &mut SeededRng,
+    agent: &mut dyn Policy,
+    player: Player,
+    grid: Grid,
+    to_move: Player,
+) -> Odds {
+    // The agent always picks the same action, random policy picks each of
+    // the actions equally likely.
+    let actions = grid.empty_fields();
+    let actions = if to_move == player {
+        vec![agent.pick(rng, grid, player, &actions)]
+    } else {
+        actions
+    };
+
+    let odds_after = |field| {
+        let grid = grid.put(field, to_move);
+        match grid.outcome() {
+            GameOutcome::Ongoing => {
+                let to_move = to_move.opponent();
+                odds_against_random(rng, agent, player, grid, to_move)
+            }
+            GameOutcome::Draw => Odds {
+                draw: 1.0,
+                ..Odds::default()
+            },
+            outcome if outcome.winner() == Some(player) => Odds {
+                win: 1.0,
+                ..Odds::default()
+            },
+            _ => Odds {
+                loss: 1.0,
+                ..Odds::default()
+            },
+        }
+    };
+
+    let p = 1.0 / actions.len() as f32;
+    actions
+        .into_iter()
+        .map(odds_after)
+        .fold(Odds::default(), |total, odds| Odds {
+            win: total.win + p * odds.win,
+            draw: total.draw + p * odds.draw,
+            loss: total.loss + p * odds.loss,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn minimax_draws_against_minimax_from_every_opening() {
+        let mut rng = SeededRng::seed_from_u64(0);
+        for player in [Player::X, Player::O] {
+            for first in [Player::X, Player::O] {
+                let tally = against_minimax(
+                    &mut rng,
+                    &mut Minimax::new(),
+                    player,
+                    first,
+                );
+                assert_eq!(tally.draws, 9 * GAMES_PER_OPENING);
+            }
+        }
+    }
+}
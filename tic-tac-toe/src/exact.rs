@@ -0,0 +1,492 @@
+//! Compares the learned values with the exact values of the grids. Tic-tac-toe
+//! is small enough to compute, for every grid the agent can move into, both
+//! what it leads to under perfect play and how likely the agent is to win
+//! from it against a random policy. The values learned against a random
+//! policy estimate the latter, so the difference shows how well they were
+//! learned, while perfect play shows where they mislead the agent.
+
+use super::analysis::{board, result};
+use super::minimax::Minimax;
+use super::{field_name, GameOutcome, Grid, Player, ValueFunction};
+use std::fmt::Write;
+
+/// How many of the worst estimated grids and of the blunders are listed.
+const SHOWN: usize = 10;
+
+/// Picks one of the exact values of a grid to compare the learned one with.
+type Exact = fn(&State) -> f32;
+
+/// The learned and exact values of one grid the agent moved into.
+#[derive(Clone, Copy, Debug)]
+pub struct State {
+    /// The grid after the agent's move.
+    pub grid: Grid,
+    /// The learned value of the grid.
+    pub learned: f32,
+    /// The probability that the agent wins from the grid playing greedily by
+    /// its values against a random policy.
+    pub against_random: f32,
+    /// Whether the grid is a win (1), a draw (0) or a loss (-1) for the agent
+    /// under perfect play.
+    pub perfect_play: i8,
+}
+
+/// A grid in which the greedy move of the agent makes the result under
+/// perfect play worse than the best move would.
+#[derive(Clone, Debug)]
+pub struct Blunder {
+    /// The grid in which the agent is to move.
+    pub grid: Grid,
+    /// The field the agent marks.
+    pub field: usize,
+    /// The result of the agent's move under perfect play, 1, 0 or -1.
+    pub result: i8,
+    /// The result of the best moves under perfect play.
+    pub best: i8,
+    /// The fields which lead to the best result.
+    pub best_fields: Vec<usize>,
+    /// How likely a game reaches the grid when the agent plays greedily
+    /// against a random policy.
+    pub reached: f32,
+}
+
+/// The exact values of all the grids the agent can move into in games which
+/// the first player starts, compared with the learned values.
+pub struct Report {
+    /// The player the values were learned for.
+    pub player: Player,
+    /// The player who moved first.
+    pub first: Player,
+    /// Every grid where the game goes on after the agent's move, ordered by
+    /// the number of marks.
+    pub states: Vec<State>,
+    /// Every grid where the agent's greedy move is a blunder, the most likely
+    /// reached first.
+    pub blunders: Vec<Blunder>,
+}
+
+impl State {
+    /// How many marks there are on the grid, i.e. how far the game is.
+    pub fn marks(&self) -> usize {
+        9 - self.grid.empty_fields().len()
+    }
+
+    /// The exact value of the grid under perfect play on the scale of the
+    /// learned values: 1.0 for a win, 0.0 for a loss and 0.5 for a draw, which
+    /// is neither.
+    pub fn perfect_value(&self) -> f32 {
+        (self.perfect_play + 1) as f32 / 2.0
+    }
+}
+
+/// Computes the exact values of every grid which can come up after a move of
+/// the player the values were learned for, in games which given player
+/// starts.
+pub fn compute(
+    values: &dyn ValueFunction,
+    player: Player,
+    first: Player,
+) -> Report {
+    // Finds every grid where the game goes on, with the player to move.
+    let mut grids = Vec::new();
+    let mut seen = vec![false; 3usize.pow(9)];
+    let mut stack = vec![(Grid::new(), first)];
+    while let Some((grid, to_move)) = stack.pop() {
+        if std::mem::replace(&mut seen[grid.to_base_10()], true)
+            || grid.outcome().is_over()
+        {
+            continue;
+        }
+        for field in grid.empty_fields() {
+            stack.push((grid.put(field, to_move), to_move.opponent()));
+        }
+        grids.push((grid, to_move));
+    }
+    grids.sort_by_key(|(grid, _)| {
+        (9 - grid.empty_fields().len(), grid.to_base_10())
+    });
+
+    // Since every move adds a mark, a grid is only reached from grids with
+    // fewer marks, so the probability of reaching it is known by the time it
+    // is visited in this order.
+    let mut reached = vec![0.0; 3usize.pow(9)];
+    reached[Grid::new().to_base_10()] = 1.0;
+    let mut minimax = Minimax::new();
+    let mut against_random = vec![None; 3usize.pow(9)];
+    let mut states = Vec::new();
+    let mut blunders = Vec::new();
+    for (grid, to_move) in grids {
+        let p = reached[grid.to_base_10()];
+        let actions = grid.empty_fields();
+        if to_move == player {
+            let field = values.best_field(grid, player, &actions);
+            reached[grid.put(field, player).to_base_10()] += p;
+            blunders.extend(blunder(&mut minimax, grid, player, field, p));
+        } else {
+            for field in &actions {
+                reached[grid.put(*field, to_move).to_base_10()] +=
+                    p / actions.len() as f32;
+            }
+            if grid != Grid::new() {
+                states.push(State {
+                    grid,
+                    learned: values.value(grid),
+                    against_random: win_against_random(
+                        values,
+                        &mut against_random,
+                        grid,
+                        player,
+                    ),
+                    perfect_play: perfect_play(
+                        &mut minimax,
+                        grid,
+                        to_move,
+                        player,
+                    ),
+                });
+            }
+        }
+    }
+
+    blunders.sort_by(|a, b| b.reached.total_cmp(&a.reached));
+    Report {
+        player,
+        first,
+        states,
+        blunders,
+    }
+}
+
+/// Whether the grid is a win (1), a draw (0) or a loss (-1) for the player
+/// under perfect play, with given player to move.
+fn perfect_play(
+    minimax: &mut Minimax,
+    grid: Grid,
+    to_move: Player,
+    player: Player,
+) -> i8 {
+    let score = minimax.score(grid, to_move).signum();
+    if to_move == player {
+        score
+    } else {
+        -score
+    }
+}
+
+/// The probability that the player wins from the grid they just moved into,
+/// when they play greedily by the values and the opponent picks each move
+/// equally likely. The probabilities are remembered by the ordinal of the
+/// grid.
+fn win_against_random(
+    values: &dyn ValueFunction,
+    known: &mut [Option<f32>],
+    grid: Grid,
+    player: Player,
+) -> f32 {
+    if let Some(p) = known[grid.to_base_10()] {
+        return p;
+    }
+
+    let actions = grid.empty_fields();
+    let total: f32 = actions
+        .iter()
+        .map(|field| {
+            let grid = grid.put(*field, player.opponent());
+            if grid.outcome().is_over() {
+                return 0.0;
+            }
+            let actions = grid.empty_fields();
+            let grid =
+                grid.put(values.best_field(grid, player, &actions), player);
+            match grid.outcome() {
+                GameOutcome::Ongoing => {
+                    win_against_random(values, known, grid, player)
+                }
+                outcome => (outcome.winner() == Some(player)) as u8 as f32,
+            }
+        })
+        .sum();
+
+    let p = total / actions.len() as f32;
+    known[grid.to_base_10()] = Some(p);
+    p
+}
+
+/// Checks whether the greedy move, marking the field in the grid where the
+/// player is to move, is a blunder. A slower win than the fastest one isn't a
+/// blunder.
+fn blunder(
+    minimax: &mut Minimax,
+    grid: Grid,
+    player: Player,
+    field: usize,
+    reached: f32,
+) -> Option<Blunder> {
+    let actions = grid.empty_fields();
+    let results: Vec<_> = actions
+        .iter()
+        .map(|field| minimax.score_action(grid, player, *field).signum())
+        .collect();
+    let best = *results.iter().max().expect("The game must not be over");
+    let result = results[actions.iter().position(|a| *a == field).unwrap()];
+    if result == best {
+        return None;
+    }
+
+    Some(Blunder {
+        grid,
+        field,
+        result,
+        best,
+        best_fields: actions
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| **result == best)
+            .map(|(field, _)| *field)
+            .collect(),
+        reached,
+    })
+}
+
+impl Report {
+    /// The summary, the mean absolute errors by the number of marks, the
+    /// worst estimated grids against each of the references and the most
+    /// likely blunders.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(
+            text,
+            "{:?} can move into {} grids where the game goes on when {:?} \
+             moves first. Its greedy move is a blunder in {} grids, and it \
+             blunders {:.3} times per game against a random policy.",
+            self.player,
+            self.states.len(),
+            self.first,
+            self.blunders.len(),
+            self.blunders
+                .iter()
+                .map(|blunder| blunder.reached)
+                .sum::<f32>()
+        )
+        .unwrap();
+
+        // The exact values the learned ones are compared with.
+        let references: [(&str, Exact); 2] = [
+            ("against a random policy", |state| state.against_random),
+            ("under perfect play", State::perfect_value),
+        ];
+        let [(_, against_random), (_, perfect)] = references;
+        let mae = |states: &[&State], exact: Exact| {
+            let total: f32 = states
+                .iter()
+                .map(|state| (state.learned - exact(state)).abs())
+                .sum();
+            total / states.len().max(1) as f32
+        };
+
+        writeln!(text, "\nMean absolute error of the learned values:").unwrap();
+        writeln!(text, "marks   grids  against random  perfect play").unwrap();
+        for marks in 1..9 {
+            let states: Vec<_> = self
+                .states
+                .iter()
+                .filter(|state| state.marks() == marks)
+                .collect();
+            if states.is_empty() {
+                continue;
+            }
+            writeln!(
+                text,
+                "{:>5} {:>7} {:>15.3} {:>13.3}",
+                marks,
+                states.len(),
+                mae(&states, against_random),
+                mae(&states, perfect)
+            )
+            .unwrap();
+        }
+        let all: Vec<_> = self.states.iter().collect();
+        writeln!(
+            text,
+            "{:>5} {:>7} {:>15.3} {:>13.3}",
+            "all",
+            all.len(),
+            mae(&all, against_random),
+            mae(&all, perfect)
+        )
+        .unwrap();
+
+        for (name, exact) in references {
+            writeln!(text, "\nWorst estimated grids {}:", name).unwrap();
+            writeln!(
+                text,
+                "board        learned  against random  perfect play"
+            )
+            .unwrap();
+            let mut worst = all.clone();
+            worst.sort_by(|a, b| {
+                let error =
+                    |state: &State| (state.learned - exact(state)).abs();
+                error(b).total_cmp(&error(a))
+            });
+            for state in worst.iter().take(SHOWN) {
+                writeln!(
+                    text,
+                    "{}  {:>7.3}  {:>14.3}  {}",
+                    board(state.grid),
+                    state.learned,
+                    state.against_random,
+                    result(state.perfect_play)
+                )
+                .unwrap();
+            }
+        }
+
+        if !self.blunders.is_empty() {
+            writeln!(text, "\nBlunders of the greedy policy:").unwrap();
+            writeln!(
+                text,
+                "board        reached  move  leads to  instead of  best moves"
+            )
+            .unwrap();
+            for blunder in self.blunders.iter().take(SHOWN) {
+                let best_fields: Vec<_> = blunder
+                    .best_fields
+                    .iter()
+                    .map(|field| field_name(*field))
+                    .collect();
+                writeln!(
+                    text,
+                    "{}  {:>6.2}%  {:>4}  {:<8}  {:<10}  {}",
+                    board(blunder.grid),
+                    100.0 * blunder.reached,
+                    field_name(blunder.field),
+                    result(blunder.result),
+                    result(blunder.best),
+                    best_fields.join(" ")
+                )
+                .unwrap();
+            }
+            if self.blunders.len() > SHOWN {
+                writeln!(text, "and {} more.", self.blunders.len() - SHOWN)
+                    .unwrap();
+            }
+        }
+        text
+    }
+
+    /// One row per grid with its number of marks, learned value and exact
+    /// values.
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("board,marks,learned,against random,perfect play\n");
+        for state in &self.states {
+            writeln!(
+                csv,
+                "{},{},{},{},{}",
+                board(state.grid),
+                state.marks(),
+                state.learned,
+                state.against_random,
+                state.perfect_play
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_values, Indexing};
+
+    fn state(grid: Grid, to_move: Player, player: Player) -> State {
+        State {
+            grid,
+            learned: 0.5,
+            against_random: 0.0,
+            perfect_play: perfect_play(
+                &mut Minimax::new(),
+                grid,
+                to_move,
+                player,
+            ),
+        }
+    }
+
+    #[test]
+    fn empty_grid_is_a_draw() {
+        let state = state(Grid::new(), Player::X, Player::X);
+        assert_eq!(state.perfect_value(), 0.5);
+    }
+
+    #[test]
+    fn grid_with_a_winning_move_is_a_win() {
+        // X completes the top row before O completes the middle one.
+        let grid = Grid::new()
+            .put(0, Player::X)
+            .put(3, Player::O)
+            .put(1, Player::X)
+            .put(4, Player::O);
+        assert_eq!(state(grid, Player::X, Player::X).perfect_value(), 1.0);
+        assert_eq!(state(grid, Player::X, Player::O).perfect_value(), 0.0);
+    }
+
+    #[test]
+    fn minimax_values_are_exact_and_never_blunder() {
+        // Every grid is valued by its result under perfect play with the
+        // opponent to move, finished ones included.
+        let player = Player::O;
+        let mut minimax = Minimax::new();
+        let mut values = initial_values(player, Indexing::Ternary);
+        for index in 0..values.estimates.len() {
+            let grid = Grid::from_base_10(index);
+            values[index] = match grid.outcome() {
+                GameOutcome::Ongoing => {
+                    let result = perfect_play(
+                        &mut minimax,
+                        grid,
+                        player.opponent(),
+                        player,
+                    );
+                    (result + 1) as f32 / 2.0
+                }
+                GameOutcome::Draw => 0.5,
+                _ => values[index],
+            };
+        }
+
+        for first in [Player::X, Player::O] {
+            let report = compute(&values, player, first);
+            assert!(report.blunders.is_empty());
+            let error: f32 = report
+                .states
+                .iter()
+                .map(|state| (state.learned - state.perfect_value()).abs())
+                .sum();
+            assert_eq!(error / report.states.len() as f32, 0.0);
+        }
+    }
+
+    #[test]
+    fn win_against_random_with_one_move_left() {
+        // X O X
+        // X O O
+        // . X .
+        // O blocks the left column with probability 1/2, then X draws.
+        // Otherwise X completes it.
+        let grid = Grid::new()
+            .put(0, Player::X)
+            .put(1, Player::O)
+            .put(2, Player::X)
+            .put(4, Player::O)
+            .put(3, Player::X)
+            .put(5, Player::O)
+            .put(7, Player::X);
+        let values = initial_values(Player::X, Indexing::Ternary);
+        let mut known = vec![None; 3usize.pow(9)];
+        let p = win_against_random(&values, &mut known, grid, Player::X);
+        assert_eq!(p, 0.5);
+    }
+}
@@ -0,0 +1,225 @@
+//! Strategies by which the learners pick exploratory moves. A learner asks its
+//! explorer before each move whether to explore. If the explorer picks a move,
+//! the learner plays it as an exploratory one, otherwise the learner plays its
+//! greedy move.
+
+use super::num_ext::*;
+use super::SeededRng;
+use rand::prelude::*;
+use std::str::FromStr;
+
+/// How exploratory moves are picked. The games are counted from the start of
+/// the run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exploration {
+    /// With the exploration probability ε, a random move is picked.
+    Constant,
+    /// ε decays linearly from the exploration probability to `end` over the
+    /// given number of games and then stays there.
+    Linear {
+        /// The final ε.
+        end: f32,
+        /// Over how many games ε decays.
+        games: u64,
+    },
+    /// ε is multiplied by `rate` after each game.
+    Exponential {
+        /// The factor ε decays by in each game.
+        rate: f32,
+    },
+    /// Each move is picked with probability proportional to
+    /// `exp(value / temperature)`. The temperature anneals linearly from
+    /// `start` to `end` over the given number of games and then stays there.
+    Softmax {
+        /// The starting temperature.
+        start: f32,
+        /// The final temperature.
+        end: f32,
+        /// Over how many games the temperature anneals.
+        games: u64,
+    },
+    /// Picks the move with the highest upper confidence bound
+    /// `value + c * sqrt(ln N / n)`, where `n` is how many times the move was
+    /// played and `N` how many times any of the moves was played.
+    Ucb {
+        /// How much the bound favours moves which were played less.
+        c: f32,
+    },
+}
+
+/// Picks the exploratory moves of one learner by its strategy. It remembers
+/// how many games were played and, for UCB, how many times each move was.
+#[derive(Clone, Debug)]
+pub struct Explorer {
+    exploration: Exploration,
+    exploration_probability: f32,
+    games: u64,
+    // Indexed by the keys the learner identifies moves by.
+    visits: Vec<u32>,
+}
+
+impl Explorer {
+    /// Creates an explorer which hasn't played any games yet. The exploration
+    /// probability is the starting ε of the strategies which use one.
+    pub fn new(exploration: Exploration, exploration_probability: f32) -> Self {
+        Self {
+            exploration,
+            exploration_probability,
+            games: 0,
+            visits: Vec::new(),
+        }
+    }
+
+    /// Decides whether to explore. Each action is given with its value and a
+    /// key which identifies it across games, e.g. the index of the afterstate.
+    /// Returns the field to mark if the move is exploratory, or `None` if the
+    /// greedy move should be played. A move picked by softmax or UCB is only
+    /// exploratory if it isn't one of the best valued moves.
+    pub fn explore(
+        &self,
+        rng: &mut SeededRng,
+        actions: &[usize],
+        mut value: impl FnMut(usize) -> (f32, usize),
+    ) -> Option<usize> {
+        let epsilon = match self.exploration {
+            Exploration::Constant => self.exploration_probability,
+            Exploration::Linear { end, games } => {
+                let progress =
+                    (self.games as f32 / games.max(1) as f32).min(1.0);
+                self.exploration_probability
+                    + progress * (end - self.exploration_probability)
+            }
+            Exploration::Exponential { rate } => {
+                self.exploration_probability * rate.powf(self.games as f32)
+            }
+            Exploration::Softmax { start, end, games } => {
+                let progress =
+                    (self.games as f32 / games.max(1) as f32).min(1.0);
+                let temperature = start + progress * (end - start);
+                let values: Vec<_> =
+                    actions.iter().map(|a| value(*a).0).collect();
+                return softmax(rng, actions, &values, temperature);
+            }
+            Exploration::Ucb { c } => {
+                let values: Vec<_> = actions
+                    .iter()
+                    .map(|a| {
+                        let (value, key) = value(*a);
+                        (value, self.visits.get(key).copied().unwrap_or(0))
+                    })
+                    .collect();
+                return ucb(actions, &values, c);
+            }
+        };
+
+        let should_explore = rng.gen_range(0.0, 1.0);
+        if should_explore <= epsilon {
+            Some(
+                *actions
+                    .choose(rng)
+                    .expect("There must be at least one action to take"),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Counts that the move with given key was played.
+    pub fn visit(&mut self, key: usize) {
+        if let Exploration::Ucb { .. } = self.exploration {
+            if key >= self.visits.len() {
+                self.visits.resize(key + 1, 0);
+            }
+            self.visits[key] += 1;
+        }
+    }
+
+    /// Counts a finished game, which the decaying strategies go by.
+    pub fn game_over(&mut self) {
+        self.games += 1;
+    }
+}
+
+/// Samples an action by the Boltzmann distribution over the values.
+fn softmax(
+    rng: &mut SeededRng,
+    actions: &[usize],
+    values: &[f32],
+    temperature: f32,
+) -> Option<usize> {
+    let best = max(values);
+    // Subtracting the best value keeps the exponentials from overflowing.
+    let weights: Vec<_> = values
+        .iter()
+        .map(|value| ((value - best) / temperature.max(f32::EPSILON)).exp())
+        .collect();
+    let mut threshold = rng.gen_range(0.0, weights.iter().sum::<f32>());
+    let picked = weights
+        .iter()
+        .position(|weight| {
+            threshold -= weight;
+            threshold < 0.0
+        })
+        .unwrap_or(weights.len() - 1);
+
+    Some(actions[picked]).filter(|_| values[picked] < best)
+}
+
+/// Picks the action with the highest upper confidence bound. Actions which
+/// were never played are picked first.
+fn ucb(actions: &[usize], values: &[(f32, u32)], c: f32) -> Option<usize> {
+    let total: u32 = values.iter().map(|(_, visits)| visits).sum();
+    let bound = |(value, visits): (f32, u32)| {
+        if visits == 0 {
+            f32::INFINITY
+        } else {
+            value + c * ((total as f32).ln() / visits as f32).sqrt()
+        }
+    };
+    let picked = (0..actions.len())
+        .max_by(|a, b| bound(values[*a]).partial_ord(bound(values[*b])))
+        .expect("There must be at least one action to take");
+
+    let best = max(&values.iter().map(|(value, _)| *value).collect::<Vec<_>>());
+    Some(actions[picked]).filter(|_| values[picked].0 < best)
+}
+
+fn max(values: &[f32]) -> f32 {
+    values.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+}
+
+impl FromStr for Exploration {
+    type Err = String;
+
+    /// Parses `constant`, `linear:<end>:<games>`, `exponential:<rate>`,
+    /// `softmax:<start>:<end>:<games>` or `ucb:<c>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Unknown exploration '{}', expected constant, \
+                 linear:<end>:<games>, exponential:<rate>, \
+                 softmax:<start>:<end>:<games> or ucb:<c>",
+                s
+            )
+        };
+        let parts: Vec<_> = s.split(':').collect();
+        let number = |i: usize| parts[i].parse::<f32>().map_err(|_| error());
+        let games = |i: usize| parts[i].parse::<u64>().map_err(|_| error());
+
+        match parts[..] {
+            ["constant"] => Ok(Self::Constant),
+            ["linear", _, _] => Ok(Self::Linear {
+                end: number(1)?,
+                games: games(2)?,
+            }),
+            ["exponential", _] => Ok(Self::Exponential { rate: number(1)? }),
+            ["softmax", _, _, _] => Ok(Self::Softmax {
+                start: number(1)?,
+                end: number(2)?,
+                games: games(3)?,
+            }),
+            ["ucb", _] => Ok(Self::Ucb { c: number(1)? }),
+            _ => Err(error()),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Lets a human play against the agent in the terminal. The fields are
+//! numbered 1 to 9 row by row, or named by their column and row:
+//!
+//! ```text
+//!     a   b   c
+//! 1   1 | 2 | 3
+//!    ---+---+---
+//! 2   4 | 5 | 6
+//!    ---+---+---
+//! 3   7 | 8 | 9
+//! ```
+//!
+//! Besides a field, the human can type `undo` to take back their last move,
+//! `hint` to be shown the best moves, `analyze` to see how the agent values
+//! each move, `resign` to give up the game and `quit` to stop playing.
+
+use std::io::{self, prelude::*};
+use tic_tac_toe::analysis;
+use tic_tac_toe::minimax::Minimax;
+use tic_tac_toe::policies::Policy;
+use tic_tac_toe::{
+    field_name, parse_field, Field, GameOutcome, Grid, Player, SeededRng,
+    ValueFunction,
+};
+
+/// How a game against the human ended.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Ending {
+    /// The game was played out or resigned, another one can follow. The
+    /// moves which weren't taken back are given in order, with the result.
+    Finished {
+        moves: Vec<usize>,
+        result: GameOutcome,
+    },
+    /// The human wants to stop playing, or there is no more input.
+    Quit,
+}
+
+/// What the human typed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Input {
+    Field(usize),
+    Undo,
+    Hint,
+    Analyze,
+    Resign,
+    Quit,
+}
+
+/// Plays one game between the human and the agent. The agent must not learn,
+/// since moves can be taken back. The values the agent plays by, along with
+/// the player they were learned for, are shown by the analysis.
+pub(super) fn play(
+    rng: &mut SeededRng,
+    input: &mut impl BufRead,
+    agent: &mut dyn Policy,
+    values: Option<(&dyn ValueFunction, Player)>,
+    human: Player,
+    first: Player,
+) -> Ending {
+    let mut minimax = Minimax::new();
+    // The grids in which the human was to move and how many moves were played
+    // by then, so that their moves can be taken back.
+    let mut history = Vec::new();
+    let mut moves = Vec::new();
+    let mut grid = Grid::new();
+    let mut player = first;
+    println!("\nNew game! You play {:?}.", human);
+
+    while !grid.outcome().is_over() {
+        if player != human {
+            let field = agent.pick(rng, grid, player, &grid.empty_fields());
+            grid = grid.put(field, player);
+            moves.push(field);
+            player = player.opponent();
+            continue;
+        }
+
+        println!();
+        print_with_labels(grid);
+        let input = match prompt(input) {
+            Some(Ok(input)) => input,
+            Some(Err(e)) => {
+                println!("{}", e);
+                continue;
+            }
+            None => {
+                println!();
+                return Ending::Quit;
+            }
+        };
+
+        match input {
+            Input::Field(field) if grid.field(field) != Field::Empty => {
+                println!("The field is already taken.");
+            }
+            Input::Field(field) => {
+                history.push((grid, moves.len()));
+                grid = grid.put(field, player);
+                moves.push(field);
+                player = player.opponent();
+            }
+            Input::Undo => match history.pop() {
+                Some((previous, played)) => {
+                    grid = previous;
+                    moves.truncate(played);
+                }
+                None => println!("There is no move to take back."),
+            },
+            Input::Hint => {
+                let scores: Vec<_> = grid
+                    .empty_fields()
+                    .into_iter()
+                    .map(|field| {
+                        (field, minimax.score_action(grid, human, field))
+                    })
+                    .collect();
+                let best = scores.iter().map(|(_, score)| *score).max();
+                let best_fields: Vec<_> = scores
+                    .iter()
+                    .filter(|(_, score)| Some(*score) == best)
+                    .map(|(field, _)| {
+                        format!("{} ({})", field + 1, field_name(*field))
+                    })
+                    .collect();
+                println!("Best moves: {}", best_fields.join(", "));
+            }
+            Input::Analyze => {
+                analysis::analyze(grid, human, values, &mut minimax)
+            }
+            Input::Resign => {
+                println!("You resigned.");
+                let result = match human {
+                    Player::X => GameOutcome::OWins,
+                    Player::O => GameOutcome::XWins,
+                };
+                return Ending::Finished { moves, result };
+            }
+            Input::Quit => return Ending::Quit,
+        }
+    }
+
+    println!();
+    grid.print();
+    match grid.outcome().winner() {
+        Some(winner) if winner == human => println!("\nYou won!"),
+        Some(_) => println!("\nYou lost."),
+        None => println!("\nIt's a draw."),
+    }
+    Ending::Finished {
+        moves,
+        result: grid.outcome(),
+    }
+}
+
+/// Reads one line of input. Returns `None` at the end of the input.
+fn prompt(input: &mut impl BufRead) -> Option<Result<Input, String>> {
+    print!("Your move: ");
+    io::stdout().flush().ok()?;
+
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(parse(line.trim())),
+    }
+}
+
+/// Parses a field number, a field name or a command.
+fn parse(s: &str) -> Result<Input, String> {
+    let s = s.to_lowercase();
+    match (parse_field(&s), s.as_str()) {
+        (Some(field), _) => Ok(Input::Field(field)),
+        (None, "undo") => Ok(Input::Undo),
+        (None, "hint") => Ok(Input::Hint),
+        (None, "analyze") => Ok(Input::Analyze),
+        (None, "resign") => Ok(Input::Resign),
+        (None, "quit") => Ok(Input::Quit),
+        _ => Err(format!(
+            "Cannot understand '{}'. Type a field from 1 to 9 or from a1 to \
+             c3, or one of undo, hint, analyze, resign and quit.",
+            s
+        )),
+    }
+}
+
+/// Prints the grid with the empty fields numbered and the columns and rows
+/// named.
+fn print_with_labels(grid: Grid) {
+    let field = |i: usize| match grid.field(i) {
+        Field::Empty => (i + 1).to_string(),
+        field => field.to_string(),
+    };
+
+    println!("    a   b   c");
+    println!("1   {} | {} | {} ", field(0), field(1), field(2));
+    println!("   ---+---+---");
+    println!("2   {} | {} | {} ", field(3), field(4), field(5));
+    println!("   ---+---+---");
+    println!("3   {} | {} | {} ", field(6), field(7), field(8));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    /// Always marks the first empty field.
+    struct FirstEmpty;
+
+    impl Policy for FirstEmpty {
+        fn pick(
+            &mut self,
+            _: &mut SeededRng,
+            _: Grid,
+            _: Player,
+            actions: &[usize],
+        ) -> usize {
+            actions[0]
+        }
+    }
+
+    /// Plays one game in which the human is X and moves first, typing the
+    /// lines of the script.
+    fn play_script(script: &str) -> Ending {
+        let mut rng = SeededRng::seed_from_u64(0);
+        let mut input = io::Cursor::new(script);
+        play(
+            &mut rng,
+            &mut input,
+            &mut FirstEmpty,
+            None,
+            Player::X,
+            Player::X,
+        )
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("undo"), Ok(Input::Undo));
+        assert_eq!(parse("hint"), Ok(Input::Hint));
+        assert_eq!(parse("analyze"), Ok(Input::Analyze));
+        assert_eq!(parse("resign"), Ok(Input::Resign));
+        assert_eq!(parse("quit"), Ok(Input::Quit));
+        assert_eq!(parse("UNDO"), Ok(Input::Undo));
+    }
+
+    #[test]
+    fn parses_fields_by_number_and_name() {
+        assert_eq!(parse("1"), Ok(Input::Field(0)));
+        assert_eq!(parse("9"), Ok(Input::Field(8)));
+        assert_eq!(parse("a1"), Ok(Input::Field(0)));
+        assert_eq!(parse("b2"), Ok(Input::Field(4)));
+        assert_eq!(parse("C3"), Ok(Input::Field(8)));
+        assert_eq!(parse("c1"), Ok(Input::Field(2)));
+        assert_eq!(parse("a3"), Ok(Input::Field(6)));
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        for s in ["", "0", "10", "d1", "a4", "1a", "b 2", "undo me", "help"] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn undo_takes_back_the_move_and_the_reply() {
+        assert_eq!(
+            play_script("b2\nundo\na1\nresign\n"),
+            Ending::Finished {
+                moves: vec![0, 1],
+                result: GameOutcome::OWins,
+            }
+        );
+        // There is nothing to take back at the start.
+        assert_eq!(
+            play_script("undo\nresign\n"),
+            Ending::Finished {
+                moves: vec![],
+                result: GameOutcome::OWins,
+            }
+        );
+    }
+
+    #[test]
+    fn taken_fields_and_unknown_input_are_asked_again() {
+        assert_eq!(
+            play_script("b2\n5\nnonsense\n9\nhint\nc1\nanalyze\na3\n"),
+            Ending::Finished {
+                moves: vec![4, 0, 8, 1, 2, 3, 6],
+                result: GameOutcome::XWins,
+            }
+        );
+    }
+
+    #[test]
+    fn quit_and_end_of_input_stop_playing() {
+        assert_eq!(play_script("5\nquit\n"), Ending::Quit);
+        assert_eq!(play_script("5\n"), Ending::Quit);
+    }
+}
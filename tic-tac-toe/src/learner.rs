@@ -0,0 +1,573 @@
+//! Learners of values of grids. Each learner is a policy which updates its
+//! values from the games it plays, and can play greedily by them once trained.
+
+use super::exploration::{Exploration, Explorer};
+use super::linear::{LinearLearner, LinearValues};
+use super::monte_carlo::{MonteCarlo, Visits};
+use super::network::{Network, NetworkLearner};
+use super::num_ext::*;
+use super::policies::Policy;
+use super::q_learning::{QLearner, Update};
+use super::symmetry::Transform;
+use super::{
+    initial_values, Grid, Indexing, Player, SeededRng, ValueFunction, Values,
+};
+use std::str::FromStr;
+
+/// The algorithms by which a learner can learn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    /// Temporal difference over the values of afterstates, see `TdLearner`.
+    Afterstate,
+    /// Off-policy temporal difference over action values, see `QLearner`.
+    QLearning,
+    /// On-policy temporal difference over action values, see `QLearner`.
+    Sarsa,
+    /// Monte Carlo over the values of afterstates, see `MonteCarlo`.
+    MonteCarlo(Visits),
+    /// Temporal difference over the weights of features of afterstates, see
+    /// `LinearLearner`.
+    Linear,
+    /// Temporal difference over the weights of a neural network which values
+    /// afterstates, see `NetworkLearner`.
+    Network,
+}
+
+/// How the eligibility of a state builds up when it is visited again. In tic
+/// tac toe, each move adds a mark, so a state is never visited twice in the
+/// same game and both kinds of traces learn the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Traces {
+    /// Each visit adds 1 to the eligibility.
+    Accumulating,
+    /// Each visit resets the eligibility to 1.
+    Replacing,
+}
+
+/// How the step size with which the value of a grid is updated changes as the
+/// grid is visited more often. Only the learners of afterstate values count
+/// the visits, the learners of action values always use a constant step size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepSizeSchedule {
+    /// The step size stays the same, so recent games weigh the most and the
+    /// values never settle.
+    Constant,
+    /// 1/n after n visits, so each value is the average of its targets.
+    SampleAverage,
+    /// 1/n^ω after n visits. ω is above 0.5 and at most 1, so that it decays
+    /// slower than the sample average and later targets weigh more, yet the
+    /// values still converge.
+    Polynomial {
+        /// The ω.
+        exponent: f32,
+    },
+}
+
+/// Settings of the learners which can be picked at runtime.
+#[derive(Clone, Copy, Debug)]
+pub struct Hyperparameters {
+    /// Akin to learning rate. Step size is a fraction which will bound the
+    /// temporal difference in value between state `s` and `s'`.
+    pub step_size: f32,
+    /// How the step size changes with the visits of a grid.
+    pub step_size_schedule: StepSizeSchedule,
+    /// Dictates how often an exploration move happens. Exploration move means
+    /// that given a list of allowed actions, one is selected at random rather
+    /// than one with the highest reward.
+    pub exploration_probability: f32,
+    /// How the exploratory moves are picked.
+    pub exploration: Exploration,
+    /// How quickly the eligibility of past states decays. With 0, only the
+    /// previous state is updated, with 1 all the states of the game are
+    /// updated equally. The learners of action values and Monte Carlo don't
+    /// use traces.
+    pub lambda: f32,
+    /// How eligibility builds up on repeated visits.
+    pub traces: Traces,
+    /// How many units there are in the hidden layer of a new network.
+    pub hidden_units: usize,
+}
+
+/// A policy which gets better as it plays.
+pub trait Learner: Policy {
+    /// Plays the best moves according to what has been learned so far. It
+    /// neither explores nor learns.
+    fn greedy(&self) -> Box<dyn Policy + '_>;
+
+    /// The learned table of values of grids, if the learner learns one.
+    fn values(&self) -> Option<&Values> {
+        None
+    }
+
+    /// The learned values of grids, whether they are kept in a table or
+    /// computed from the grid, if the learner learns them.
+    fn value_function(&self) -> Option<&dyn ValueFunction> {
+        self.values().map(|values| values as &dyn ValueFunction)
+    }
+
+    /// The learned network, if the learner learns one.
+    fn network(&self) -> Option<&Network> {
+        None
+    }
+}
+
+impl Algorithm {
+    /// Creates a new learner which uses this algorithm. The initial weights of
+    /// a network are drawn from the generator.
+    pub fn learner(
+        self,
+        rng: &mut SeededRng,
+        player: Player,
+        indexing: Indexing,
+        hyperparameters: Hyperparameters,
+    ) -> Box<dyn Learner> {
+        match self {
+            Self::QLearning => Box::new(QLearner::new(
+                Update::QLearning,
+                indexing,
+                hyperparameters,
+            )),
+            Self::Sarsa => Box::new(QLearner::new(
+                Update::Sarsa,
+                indexing,
+                hyperparameters,
+            )),
+            Self::Linear => Box::new(LinearLearner::new(
+                LinearValues::new(player),
+                hyperparameters,
+            )),
+            Self::Network => Box::new(NetworkLearner::new(
+                Network::new(rng, player, hyperparameters.hidden_units),
+                hyperparameters,
+            )),
+            _ => self
+                .learner_with_values(
+                    initial_values(player, indexing),
+                    hyperparameters,
+                )
+                .expect("The algorithm learns values"),
+        }
+    }
+
+    /// Whether the algorithm learns a table of values of grids, as opposed to
+    /// values of actions or weights.
+    pub fn learns_values(self) -> bool {
+        match self {
+            Self::Afterstate | Self::MonteCarlo(_) => true,
+            Self::QLearning | Self::Sarsa | Self::Linear | Self::Network => {
+                false
+            }
+        }
+    }
+
+    /// Creates a learner which uses this algorithm and continues from given
+    /// values. Returns `None` if the algorithm doesn't learn a table of values
+    /// of grids.
+    pub fn learner_with_values(
+        self,
+        values: Values,
+        hyperparameters: Hyperparameters,
+    ) -> Option<Box<dyn Learner>> {
+        match self {
+            Self::Afterstate => {
+                Some(Box::new(TdLearner::new(values, hyperparameters)))
+            }
+            Self::MonteCarlo(visits) => {
+                Some(Box::new(MonteCarlo::new(values, visits, hyperparameters)))
+            }
+            Self::QLearning | Self::Sarsa | Self::Linear | Self::Network => {
+                None
+            }
+        }
+    }
+}
+
+impl Default for Hyperparameters {
+    /// Without traces, i.e. TD(0).
+    fn default() -> Self {
+        Self {
+            step_size: 0.2,
+            step_size_schedule: StepSizeSchedule::Constant,
+            exploration_probability: 0.01,
+            exploration: Exploration::Constant,
+            lambda: 0.0,
+            traces: Traces::Accumulating,
+            hidden_units: 40,
+        }
+    }
+}
+
+impl Hyperparameters {
+    /// The step size for a grid which has been visited given number of times.
+    pub fn step_size_after(&self, visits: u32) -> f32 {
+        let visits = visits.max(1) as f32;
+        match self.step_size_schedule {
+            StepSizeSchedule::Constant => self.step_size,
+            StepSizeSchedule::SampleAverage => 1.0 / visits,
+            StepSizeSchedule::Polynomial { exponent } => visits.powf(-exponent),
+        }
+    }
+}
+
+impl FromStr for StepSizeSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Unknown step size schedule '{}', expected constant, \
+                 sample-average or polynomial:<exponent>",
+                s
+            )
+        };
+        match s.split(':').collect::<Vec<_>>()[..] {
+            ["constant"] => Ok(Self::Constant),
+            ["sample-average"] => Ok(Self::SampleAverage),
+            ["polynomial", exponent] => exponent
+                .parse()
+                .ok()
+                .filter(|exponent| *exponent > 0.5 && *exponent <= 1.0)
+                .map(|exponent| Self::Polynomial { exponent })
+                .ok_or_else(|| {
+                    "The exponent of the polynomial step size schedule must \
+                     be a number above 0.5 and at most 1"
+                        .to_string()
+                }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl FromStr for Traces {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accumulating" => Ok(Self::Accumulating),
+            "replacing" => Ok(Self::Replacing),
+            _ => Err(format!(
+                "Unknown traces '{}', expected accumulating or replacing",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "afterstate" => Ok(Self::Afterstate),
+            "q-learning" => Ok(Self::QLearning),
+            "sarsa" => Ok(Self::Sarsa),
+            "mc-first-visit" => Ok(Self::MonteCarlo(Visits::First)),
+            "mc-every-visit" => Ok(Self::MonteCarlo(Visits::Every)),
+            "linear" => Ok(Self::Linear),
+            "network" => Ok(Self::Network),
+            _ => Err(format!(
+                "Unknown algorithm '{}', expected afterstate, q-learning, \
+                sarsa, mc-first-visit, mc-every-visit, linear or network",
+                s
+            )),
+        }
+    }
+}
+
+/// Learns a value for each grid by temporal difference as it plays. The values
+/// are estimates of how likely it is to win from a grid in which the learner
+/// has just put its mark, i.e. from an afterstate.
+///
+/// Each temporal difference is applied to all the states of the current game
+/// in proportion to their eligibility, i.e. this is TD(λ). The eligibility of
+/// a state decays by λ with each move.
+pub struct TdLearner {
+    /// The values of the grids after the learner's moves.
+    pub values: Values,
+    hyperparameters: Hyperparameters,
+    explorer: Explorer,
+    // Index of the grid which resulted from the learner's previous move in
+    // the current game. This is the state whose value gets updated next.
+    last_afterstate: Option<usize>,
+    // Indices of the states visited in the current game and their
+    // eligibility.
+    eligibility: Vec<(usize, f32)>,
+}
+
+impl TdLearner {
+    /// Creates a learner which continues learning given values.
+    pub fn new(values: Values, hyperparameters: Hyperparameters) -> Self {
+        Self {
+            values,
+            hyperparameters,
+            explorer: Explorer::new(
+                hyperparameters.exploration,
+                hyperparameters.exploration_probability,
+            ),
+            last_afterstate: None,
+            eligibility: Vec::new(),
+        }
+    }
+
+    /// Updates the values of the states visited so far by the temporal
+    /// difference between the previous state and the next one.
+    fn backup(&mut self, state_index: usize, next_state_value: f32) {
+        let Hyperparameters { lambda, traces, .. } = self.hyperparameters;
+        let td_error = next_state_value - self.values[state_index];
+
+        match self.eligibility.iter_mut().find(|(i, _)| *i == state_index) {
+            Some((_, e)) if traces == Traces::Accumulating => *e += 1.0,
+            Some((_, e)) => *e = 1.0,
+            None => self.eligibility.push((state_index, 1.0)),
+        }
+
+        for (index, e) in &mut self.eligibility {
+            let step_size = self
+                .hyperparameters
+                .step_size_after(self.values.visits[*index]);
+            self.values[*index] += step_size * td_error * *e;
+            *e *= lambda;
+        }
+
+        // With λ = 0 there is nothing left to remember.
+        self.eligibility.retain(|(_, e)| *e > 0.0);
+    }
+}
+
+impl Policy for TdLearner {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        // Asks the explorer whether it should do an exploratory move.
+        // Exploratory moves don't update any values, they only move the
+        // learner to a new state. The states before an exploratory move are
+        // not to blame for what comes after it, hence their eligibility is
+        // forgotten.
+        let values = &self.values;
+        let explored = self.explorer.explore(rng, actions, |field| {
+            let index = values.index(grid.put(field, player));
+            (values[index], index)
+        });
+        if let Some(field_to_mark) = explored {
+            self.eligibility.clear();
+            let index = self.values.index(grid.put(field_to_mark, player));
+            self.values.visits[index] += 1;
+            self.explorer.visit(index);
+            self.last_afterstate = Some(index);
+            return field_to_mark;
+        }
+
+        let (field_to_mark, next_state_index) =
+            best_action(&self.values, grid, player, actions);
+        self.values.visits[next_state_index] += 1;
+        self.explorer.visit(next_state_index);
+
+        // Updates the value of the previous state to be closer to the next
+        // state by using the temporal difference.
+        if let Some(state_index) = self.last_afterstate {
+            self.backup(state_index, self.values[next_state_index]);
+        }
+
+        self.last_afterstate = Some(next_state_index);
+        field_to_mark
+    }
+
+    fn game_over(&mut self, grid: Grid, _: Player) {
+        // If the opponent made the last move, the state we left them with is
+        // updated towards the final state. Final states are never updated
+        // so their values are exact: 1.0 for a win and 0.0 for a draw or a
+        // loss.
+        if let Some(state_index) = self.last_afterstate.take() {
+            let final_index = self.values.index(grid);
+            if state_index != final_index {
+                self.backup(state_index, self.values[final_index]);
+            }
+        }
+        self.eligibility.clear();
+        self.explorer.game_over();
+    }
+}
+
+impl Learner for TdLearner {
+    fn greedy(&self) -> Box<dyn Policy + '_> {
+        Box::new(Greedy {
+            values: &self.values,
+        })
+    }
+
+    fn values(&self) -> Option<&Values> {
+        Some(&self.values)
+    }
+}
+
+/// Finds the action which leads to the grid with the highest value. Returns
+/// the field to mark and the index of the grid after the action.
+pub fn best_action(
+    values: &Values,
+    grid: Grid,
+    player: Player,
+    actions: &[usize],
+) -> (usize, usize) {
+    // With canonical values, the actions are compared on the canonical grid
+    // and the best one is mapped back onto the actual grid. Ties are then
+    // broken the same way in every orientation of the grid, so the agent
+    // plays symmetrical positions symmetrically.
+    let transform = match values.indexing {
+        Indexing::Ternary | Indexing::Reachable => Transform::Identity,
+        Indexing::Canonical => grid.canonical().1,
+    };
+    let grid = transform.apply(grid);
+    let mut actions: Vec<_> =
+        actions.iter().map(|a| transform.map_field(*a)).collect();
+    actions.sort_unstable();
+
+    let (field_to_mark, index) = actions
+        .into_iter()
+        .map(|field_to_mark| {
+            let grid_after_action = grid.put(field_to_mark, player);
+            (field_to_mark, values.index(grid_after_action))
+        })
+        .max_by(|(_, index), (_, another_index)| {
+            values[*index].partial_ord(values[*another_index])
+        })
+        .expect("There must be at least one action to take");
+
+    (transform.inverse().map_field(field_to_mark), index)
+}
+
+/// Always picks the action with the highest value and never learns. This is
+/// how a trained learner is evaluated.
+pub struct Greedy<'a> {
+    /// The values the policy goes by.
+    pub values: &'a dyn ValueFunction,
+}
+
+impl Policy for Greedy<'_> {
+    fn pick(
+        &mut self,
+        _rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        self.values.best_field(grid, player, actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learner(lambda: f32, traces: Traces) -> TdLearner {
+        TdLearner::new(
+            initial_values(Player::X, Indexing::Ternary),
+            Hyperparameters {
+                step_size: 0.1,
+                lambda,
+                traces,
+                ..Hyperparameters::default()
+            },
+        )
+    }
+
+    /// Two afterstates of X in one game, the second after the first.
+    fn afterstates() -> (usize, usize) {
+        let first = Grid::new().put(4, Player::X);
+        let second = first.put(0, Player::O).put(8, Player::X);
+        (first.to_base_10(), second.to_base_10())
+    }
+
+    fn step_sizes(schedule: StepSizeSchedule) -> Vec<f32> {
+        let hyperparameters = Hyperparameters {
+            step_size: 0.2,
+            step_size_schedule: schedule,
+            ..Hyperparameters::default()
+        };
+        [0, 1, 4, 16]
+            .iter()
+            .map(|visits| hyperparameters.step_size_after(*visits))
+            .collect()
+    }
+
+    #[test]
+    fn step_size_follows_schedule() {
+        assert_eq!(
+            step_sizes(StepSizeSchedule::Constant),
+            [0.2, 0.2, 0.2, 0.2]
+        );
+        // Before the first visit, the step size is the one of the first.
+        assert_eq!(
+            step_sizes(StepSizeSchedule::SampleAverage),
+            [1.0, 1.0, 0.25, 0.0625]
+        );
+        assert_eq!(
+            step_sizes(StepSizeSchedule::Polynomial { exponent: 0.75 }),
+            [1.0, 1.0, 0.35355338, 0.125]
+        );
+    }
+
+    #[test]
+    fn polynomial_exponent_must_be_above_half_and_at_most_one() {
+        assert_eq!(
+            "polynomial:1".parse(),
+            Ok(StepSizeSchedule::Polynomial { exponent: 1.0 })
+        );
+        assert_eq!(
+            "polynomial:0.6".parse(),
+            Ok(StepSizeSchedule::Polynomial { exponent: 0.6 })
+        );
+        for exponent in ["0.5", "0", "-1", "1.5", "NaN", "inf", "x", ""] {
+            let schedule = format!("polynomial:{}", exponent);
+            assert!(
+                schedule.parse::<StepSizeSchedule>().is_err(),
+                "{} was accepted",
+                schedule
+            );
+        }
+    }
+
+    #[test]
+    fn backup_without_traces_is_one_step_update() {
+        let (state, _) = afterstates();
+        let mut learner = learner(0.0, Traces::Accumulating);
+        learner.backup(state, 1.0);
+        assert_eq!(learner.values[state], 0.5 + 0.1 * (1.0 - 0.5));
+        assert!(learner.eligibility.is_empty());
+    }
+
+    #[test]
+    fn traces_differ_on_revisited_state() {
+        let (state, _) = afterstates();
+        let mut accumulating = learner(0.5, Traces::Accumulating);
+        let mut replacing = learner(0.5, Traces::Replacing);
+        for learner in [&mut accumulating, &mut replacing] {
+            learner.backup(state, 1.0);
+            learner.backup(state, 1.0);
+        }
+        // The first backup leaves 0.55 with the eligibility decayed to 0.5.
+        // The second one adds 1 to it or resets it to 1.
+        assert_eq!(accumulating.values[state], 0.55 + 0.1 * 0.45 * 1.5);
+        assert_eq!(replacing.values[state], 0.55 + 0.1 * 0.45 * 1.0);
+    }
+
+    #[test]
+    fn final_reward_reaches_earlier_afterstates_with_traces() {
+        let (first, second) = afterstates();
+        for lambda in [0.0, 0.5] {
+            let mut learner = learner(lambda, Traces::Accumulating);
+            let value = learner.values[second];
+            learner.backup(first, value);
+            learner.backup(second, 1.0);
+            assert_eq!(
+                learner.values[first],
+                0.5 + 0.1 * lambda * (1.0 - 0.5),
+                "λ = {}",
+                lambda
+            );
+        }
+    }
+}
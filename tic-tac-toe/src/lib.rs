@@ -0,0 +1,726 @@
+#![warn(missing_docs)]
+//! An agent which learns to play tic-tac-toe by reinforcement learning, as
+//! described in the first chapter of Reinforcement Learning: An Introduction
+//! by Sutton and Barto.
+//!
+//! The game itself is made of a [`Grid`] of [`Field`]s which the two
+//! [`Player`]s put their marks on, until [`Grid::outcome`] says the game is
+//! over. A [`Values`] table ranks each grid and [`initial_values`] creates the
+//! table an agent starts learning from. The [`linear`] module ranks grids by
+//! their features instead and the [`network`] module by a neural network, and
+//! the agent plays by any of them as a [`ValueFunction`]. [`play_game`] plays
+//! one game between two [`policies::Policy`]s, letting them learn from it. The
+//! [`learner`] module has the policies which learn values and the other modules
+//! the policies which don't, such as [`minimax`] and [`mcts`], along with the
+//! tools to train, evaluate, store and compare them.
+//!
+//! ```no_run
+//! use tic_tac_toe::learner::{Algorithm, Hyperparameters};
+//! use tic_tac_toe::policies::Random;
+//! use tic_tac_toe::{play_game, Indexing, Player, SeededRng};
+//! use rand::SeedableRng;
+//!
+//! let mut rng = SeededRng::seed_from_u64(42);
+//! let mut agent = Algorithm::Afterstate.learner(
+//!     &mut rng,
+//!     Player::X,
+//!     Indexing::Ternary,
+//!     Hyperparameters::default(),
+//! );
+//! for _ in 0..1000 {
+//!     play_game(&mut rng, &mut agent, &mut Random, Player::X);
+//! }
+//! let grid = play_game(&mut rng, &mut agent.greedy(), &mut Random, Player::X);
+//! println!("{:?}", grid.outcome());
+//! ```
+
+pub mod analysis;
+pub mod evaluation;
+pub mod exact;
+pub mod exploration;
+pub mod learner;
+pub mod linear;
+pub mod mcts;
+pub mod minimax;
+pub mod monte_carlo;
+pub mod network;
+mod num_ext;
+pub mod policies;
+pub mod q_learning;
+pub mod reachable;
+pub mod record;
+pub mod semi_gradient;
+pub mod storage;
+pub mod symmetry;
+pub mod tournament;
+pub mod training;
+
+use num_ext::*;
+use policies::Policy;
+use rand::prelude::*;
+use reachable::ReachableGrids;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+/// All randomness comes from this generator. It is seeded, therefore a run can
+/// be reproduced by running it with the same seed.
+pub type SeededRng = StdRng;
+
+/// Tic-tac-toe is played on 3x3 grid. Since there are 9 fields and each field
+/// can be in 3 states, there are 3^9 = 19683 distinct grids.
+///
+/// Grid is sometimes referred to more generally as state.
+///
+/// Each player's marks are kept as a bitboard, a 9 bit mask in which the bit
+/// at given index is set if the player has their mark on the field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    x: u16,
+    o: u16,
+}
+
+/// All the 9 fields of the grid.
+const FULL: u16 = 0b111_111_111;
+
+/// The 8 ways to get 3 in a row: the rows, the columns and the diagonals.
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+
+/// The ternary ordinal of a grid in which the set bits of the mask hold 1 and
+/// the other fields 0, i.e. the sum of 3^i over the set bits i. The ordinal
+/// of any grid is then a sum of two lookups.
+const TERNARY: [u16; 512] = ternary_table();
+
+const fn ternary_table() -> [u16; 512] {
+    let mut table = [0; 512];
+    let mut mask = 0;
+    while mask < 512 {
+        let mut power = 1;
+        let mut field = 0;
+        while field < 9 {
+            if mask & (1 << field) != 0 {
+                table[mask] += power;
+            }
+            power *= 3;
+            field += 1;
+        }
+        mask += 1;
+    }
+    table
+}
+
+/// Each tic-tac-toe grid field can either have an X, an O or be empty.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    /// Nobody has marked the field yet.
+    Empty,
+    /// Marked by O.
+    O,
+    /// Marked by X.
+    X,
+}
+
+/// The two players.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Player {
+    /// The player who marks fields with X.
+    X,
+    /// The player who marks fields with O.
+    O,
+}
+
+/// How a game ended, or that it hasn't ended yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOutcome {
+    /// X has 3 in a row.
+    XWins,
+    /// O has 3 in a row.
+    OWins,
+    /// All the fields are marked and nobody has 3 in a row.
+    Draw,
+    /// The game goes on.
+    Ongoing,
+}
+
+/// Value vector holds a ranking (how favorable it is) for each grid. There are
+/// 19683 distinct states that the grid can be in, but only 8533 of them can
+/// come up in a game. The indexing decides which grids have a position in the
+/// vector and what it is.
+#[derive(Clone, Debug)]
+pub struct Values {
+    /// How the grids are mapped to their positions in the estimates.
+    pub indexing: Indexing,
+    /// The estimated probability of winning from each grid, by position.
+    pub estimates: Vec<f32>,
+    /// How many times has the learner put its mark such that it ended up in
+    /// each grid. Step size schedules use it to settle the values of well
+    /// known grids.
+    pub visits: Vec<u32>,
+}
+
+/// Estimates how likely the player the values were learned for is to win from
+/// a grid in which they have just put their mark. A table of `Values` is one
+/// such estimate, a function of features of the grid is another, and the
+/// agent plays by either of them the same way.
+pub trait ValueFunction {
+    /// The estimated value of the grid.
+    fn value(&self, grid: Grid) -> f32;
+
+    /// The value of the grid for the mover who just moved into it. The values
+    /// were learned for given player, the other player's grids are looked up
+    /// with the marks swapped.
+    fn afterstate_value(
+        &self,
+        grid: Grid,
+        mover: Player,
+        player: Player,
+    ) -> f32 {
+        if mover == player {
+            self.value(grid)
+        } else {
+            self.value(grid.swap_players())
+        }
+    }
+
+    /// The field to mark which leads to the grid with the highest value.
+    fn best_field(
+        &self,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        *actions
+            .iter()
+            .max_by(|a, b| {
+                let a = self.value(grid.put(**a, player));
+                let b = self.value(grid.put(**b, player));
+                a.partial_ord(b)
+            })
+            .expect("There must be at least one action to take")
+    }
+}
+
+/// How a grid is mapped to its position in the values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indexing {
+    /// Converts the grid from radix 3 to radix 10, therefore each grid has its
+    /// own value.
+    Ternary,
+    /// Symmetrical grids share the value of their canonical grid. The agent
+    /// learns about all of them at once.
+    Canonical,
+    /// Only grids which are reachable in legal play have a value. Each is
+    /// given a dense slot, so the vector is more than twice shorter.
+    Reachable,
+}
+
+impl Grid {
+    /// Creates a new state where each field is set to empty.
+    pub fn new() -> Self {
+        Self { x: 0, o: 0 }
+    }
+
+    /// What is on the field with given index.
+    pub fn field(self, field_index: usize) -> Field {
+        let bit = 1 << field_index;
+        if self.x & bit != 0 {
+            Field::X
+        } else if self.o & bit != 0 {
+            Field::O
+        } else {
+            Field::Empty
+        }
+    }
+
+    /// All the fields row by row.
+    pub fn fields(self) -> [Field; 9] {
+        let mut fields = [Field::Empty; 9];
+        for (i, field) in fields.iter_mut().enumerate() {
+            *field = self.field(i);
+        }
+        fields
+    }
+
+    /// The same grid with the field at given index set to given state,
+    /// whatever was on it before.
+    pub fn with_field(mut self, field_index: usize, field: Field) -> Self {
+        debug_assert!(field_index < 9);
+        let bit = 1 << field_index;
+        self.x &= !bit;
+        self.o &= !bit;
+        match field {
+            Field::X => self.x |= bit,
+            Field::O => self.o |= bit,
+            Field::Empty => (),
+        }
+        self
+    }
+
+    /// Puts given player's mark on given field.
+    /// ```text
+    ///  0 | 1 | 2
+    /// ---+---+---
+    ///  3 | 4 | 5
+    /// ---+---+---
+    ///  6 | 7 | 8
+    /// ```
+    pub fn put(self, field_index: usize, player: Player) -> Self {
+        self.with_field(field_index, player.into())
+    }
+
+    /// The marks of given player.
+    pub fn marks(self, player: Player) -> u16 {
+        match player {
+            Player::X => self.x,
+            Player::O => self.o,
+        }
+    }
+
+    /// Returns indices of all fields which are still empty, in ascending order.
+    pub fn empty_fields(self) -> Vec<usize> {
+        let mut empty = !(self.x | self.o) & FULL;
+        let mut fields = Vec::with_capacity(empty.count_ones() as usize);
+        while empty != 0 {
+            fields.push(empty.trailing_zeros() as usize);
+            // Clears the lowest set bit.
+            empty &= empty - 1;
+        }
+        fields
+    }
+
+    /// Prints the grid into console.
+    pub fn print(self) {
+        let s = self.fields();
+        println!(" {} | {} | {} ", s[0], s[1], s[2]);
+        println!("---+---+---");
+        println!(" {} | {} | {} ", s[3], s[4], s[5]);
+        println!("---+---+---");
+        println!(" {} | {} | {} ", s[6], s[7], s[8]);
+    }
+
+    /// Tells whether the game is over, and if so, how it ended.
+    pub fn outcome(self) -> GameOutcome {
+        // A legal grid can't have both players with 3 in a row. If it happens
+        // anyway, X is arbitrarily considered to be the winner.
+        if self.has_three_in_a_row(Player::X) {
+            GameOutcome::XWins
+        } else if self.has_three_in_a_row(Player::O) {
+            GameOutcome::OWins
+        } else if self.x | self.o == FULL {
+            GameOutcome::Draw
+        } else {
+            GameOutcome::Ongoing
+        }
+    }
+
+    /// Winning is of course determined by having 3 in a row (either
+    /// vertically, horizontally or diagonally).
+    pub fn has_three_in_a_row(self, player: Player) -> bool {
+        let marks = self.marks(player);
+        LINES.iter().any(|line| marks & line == *line)
+    }
+
+    /// The same grid with each X replaced by an O and vice versa.
+    pub fn swap_players(self) -> Self {
+        Self {
+            x: self.o,
+            o: self.x,
+        }
+    }
+
+    /// Calculates the position of the grid in the vector of values. It amounts
+    /// to treating each of the 9 fields of the grid as a numeral in ternary
+    /// system (base 3). Then each field state is arbitrarily assigned a value
+    /// 0, 1 or 2.
+    pub fn to_base_10(self) -> usize {
+        usize::from(TERNARY[usize::from(self.x)]) * Field::X.as_usize()
+            + usize::from(TERNARY[usize::from(self.o)]) * Field::O.as_usize()
+    }
+
+    /// Converts a decimal number into ternary system (base 3). Then from the
+    /// system creates a grid thanks to an arbitrary conversion between 0, 1, 2
+    /// and X, O, empty field.
+    pub fn from_base_10(mut ordinal: usize) -> Self {
+        debug_assert!(ordinal < 3usize.pow(9));
+        let mut grid = Self::new();
+        for field_index in 0..9 {
+            grid = grid.with_field(field_index, Field::from_usize(ordinal % 3));
+            ordinal /= 3;
+        }
+        grid
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameOutcome {
+    /// The player who won, if any.
+    pub fn winner(self) -> Option<Player> {
+        match self {
+            Self::XWins => Some(Player::X),
+            Self::OWins => Some(Player::O),
+            Self::Draw | Self::Ongoing => None,
+        }
+    }
+
+    /// Whether the game has ended, with a win or a draw.
+    pub fn is_over(self) -> bool {
+        self != Self::Ongoing
+    }
+}
+
+impl Player {
+    /// The other player.
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::X => Self::O,
+            Self::O => Self::X,
+        }
+    }
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x" | "X" => Ok(Self::X),
+            "o" | "O" => Ok(Self::O),
+            _ => Err(format!("Unknown player '{}', expected x or o", s)),
+        }
+    }
+}
+
+impl PartialEq<Player> for Field {
+    /// Does the player own the field's mark?
+    fn eq(&self, player: &Player) -> bool {
+        match self {
+            Self::O => *player == Player::O,
+            Self::X => *player == Player::X,
+            Self::Empty => false,
+        }
+    }
+}
+
+impl From<Player> for Field {
+    fn from(player: Player) -> Self {
+        match player {
+            Player::X => Self::X,
+            Player::O => Self::O,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::X => write!(f, "X"),
+            Self::O => write!(f, "O"),
+            Self::Empty => write!(f, " "),
+        }
+    }
+}
+
+impl Field {
+    /// Arbitrary conversion of `Field` into `usize`. Must match the
+    /// `Field::from_usize` method.
+    fn as_usize(self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::O => 1,
+            Self::X => 2,
+        }
+    }
+
+    /// Arbitrary conversion of `usize` into `Field`. Must match the
+    /// `Field::as_usize` method.
+    fn from_usize(u: usize) -> Self {
+        match u {
+            0 => Self::Empty,
+            1 => Self::O,
+            2 => Self::X,
+            _ => panic!("Field can only be created from 0, 1 or 2."),
+        }
+    }
+}
+
+impl Indexing {
+    /// Position of the grid in the values. Panics if the indexing has no
+    /// position for the grid.
+    pub fn index(self, grid: Grid) -> usize {
+        match self {
+            Self::Ternary => grid.to_base_10(),
+            Self::Canonical => grid.canonical_base_10(),
+            Self::Reachable => ReachableGrids::get()
+                .slot(grid)
+                .expect("The grid cannot be reached in legal play"),
+        }
+    }
+
+    /// How many positions are there in the values.
+    pub(crate) fn len(self) -> usize {
+        match self {
+            Self::Ternary | Self::Canonical => 3usize.pow(9),
+            Self::Reachable => ReachableGrids::get().grids.len(),
+        }
+    }
+
+    /// The grid whose value is at given position. With canonical indexing,
+    /// only the positions of canonical grids are ever used.
+    pub fn grid(self, index: usize) -> Grid {
+        match self {
+            Self::Ternary | Self::Canonical => Grid::from_base_10(index),
+            Self::Reachable => ReachableGrids::get().grids[index],
+        }
+    }
+}
+
+impl Values {
+    /// Position of the grid in the values.
+    pub fn index(&self, grid: Grid) -> usize {
+        self.indexing.index(grid)
+    }
+
+    /// Converts the values to another indexing. Grids which had no value
+    /// are given their initial value.
+    pub fn reindex(&self, indexing: Indexing, player: Player) -> Self {
+        let mut values = initial_values(player, indexing);
+        for index in 0..indexing.len() {
+            let grid = indexing.grid(index);
+            let known = match self.indexing {
+                Indexing::Reachable => ReachableGrids::get().slot(grid),
+                _ => Some(self.index(grid)),
+            };
+            if let Some(known) = known {
+                values[index] = self[known];
+                values.visits[index] = self.visits[known];
+            }
+        }
+
+        values
+    }
+}
+
+impl ValueFunction for Values {
+    fn value(&self, grid: Grid) -> f32 {
+        self[self.index(grid)]
+    }
+
+    /// Ties are broken the same way in every orientation of the grid, see
+    /// `learner::best_action`.
+    fn best_field(
+        &self,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        learner::best_action(self, grid, player, actions).0
+    }
+}
+
+impl Index<usize> for Values {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &f32 {
+        &self.estimates[index]
+    }
+}
+
+impl IndexMut<usize> for Values {
+    fn index_mut(&mut self, index: usize) -> &mut f32 {
+        &mut self.estimates[index]
+    }
+}
+
+/// Creates a vector with initial values for each state. Each state where the
+/// game is still on is rated 0.5. Each state where given player won is set to
+/// 1.0. Each state where the opponent won or which is a draw is set to 0.0.
+pub fn initial_values(player: Player, indexing: Indexing) -> Values {
+    let estimates = (0..indexing.len())
+        .map(|index| match indexing.grid(index).outcome() {
+            GameOutcome::Ongoing => 0.5,
+            outcome if outcome.winner() == Some(player) => 1.0,
+            _ => 0.0,
+        })
+        .collect();
+
+    Values {
+        indexing,
+        estimates,
+        visits: vec![0; indexing.len()],
+    }
+}
+
+/// Plays one game between two policies, starting with the first player. Once
+/// the game is over, both policies are told about the final grid so that they
+/// can learn from it. Returns the final grid.
+pub fn play_game(
+    rng: &mut SeededRng,
+    x: &mut dyn Policy,
+    o: &mut dyn Policy,
+    first: Player,
+) -> Grid {
+    play_recorded_game(rng, x, o, first).0
+}
+
+/// Plays one game like `play_game`. Returns the final grid and the fields
+/// marked in order.
+pub fn play_recorded_game(
+    rng: &mut SeededRng,
+    x: &mut dyn Policy,
+    o: &mut dyn Policy,
+    first: Player,
+) -> (Grid, Vec<usize>) {
+    let mut moves = Vec::with_capacity(9);
+    let mut grid = Grid::new();
+    let mut player = first;
+    loop {
+        let actions = grid.empty_fields();
+        let field_to_mark = match player {
+            Player::X => x.pick(rng, grid, player, &actions),
+            Player::O => o.pick(rng, grid, player, &actions),
+        };
+        debug_assert_eq!(Field::Empty, grid.field(field_to_mark));
+        grid = grid.put(field_to_mark, player);
+        moves.push(field_to_mark);
+
+        if grid.outcome().is_over() {
+            break;
+        }
+
+        player = player.opponent();
+    }
+
+    x.game_over(grid, Player::X);
+    o.game_over(grid, Player::O);
+
+    (grid, moves)
+}
+
+/// The name of the field by its column and row, e.g. b2 for the middle.
+pub fn field_name(field: usize) -> String {
+    format!("{}{}", (b'a' + (field % 3) as u8) as char, field / 3 + 1)
+}
+
+/// Parses a field number from 1 to 9, or a field name from a1 to c3.
+pub fn parse_field(s: &str) -> Option<usize> {
+    match s.to_lowercase().as_bytes() {
+        [number @ b'1'..=b'9'] => Some(usize::from(number - b'1')),
+        [column @ b'a'..=b'c', row @ b'1'..=b'3'] => {
+            Some(usize::from(row - b'1') * 3 + usize::from(column - b'a'))
+        }
+        _ => None,
+    }
+}
+
+/// Orders the two policies into X and O seats given the player the first one
+/// plays.
+pub fn seats<'a>(
+    player: Player,
+    a: &'a mut dyn Policy,
+    b: &'a mut dyn Policy,
+) -> (&'a mut dyn Policy, &'a mut dyn Policy) {
+    match player {
+        Player::X => (a, b),
+        Player::O => (b, a),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fields of the grid with given ordinal, decoded digit by digit.
+    fn reference_fields(mut ordinal: usize) -> [Field; 9] {
+        let mut fields = [Field::Empty; 9];
+        for field in fields.iter_mut() {
+            *field = match ordinal % 3 {
+                0 => Field::Empty,
+                1 => Field::O,
+                _ => Field::X,
+            };
+            ordinal /= 3;
+        }
+        fields
+    }
+
+    /// Checks the rows, the columns and the diagonals one by one, the way the
+    /// grid did before it became a bitboard.
+    fn reference_outcome(fields: [Field; 9]) -> GameOutcome {
+        const LINES: [[usize; 3]; 8] = [
+            [0, 1, 2],
+            [3, 4, 5],
+            [6, 7, 8],
+            [0, 3, 6],
+            [1, 4, 7],
+            [2, 5, 8],
+            [0, 4, 8],
+            [2, 4, 6],
+        ];
+        let wins = |player: Player| {
+            LINES
+                .iter()
+                .any(|line| line.iter().all(|field| fields[*field] == player))
+        };
+        if wins(Player::X) {
+            GameOutcome::XWins
+        } else if wins(Player::O) {
+            GameOutcome::OWins
+        } else if fields.iter().all(|field| *field != Field::Empty) {
+            GameOutcome::Draw
+        } else {
+            GameOutcome::Ongoing
+        }
+    }
+
+    #[test]
+    fn base_10_round_trips() {
+        for ordinal in 0..3usize.pow(9) {
+            let grid = Grid::from_base_10(ordinal);
+            assert_eq!(grid.fields(), reference_fields(ordinal));
+            assert_eq!(grid.to_base_10(), ordinal);
+        }
+    }
+
+    #[test]
+    fn outcome_matches_reference() {
+        for ordinal in 0..3usize.pow(9) {
+            let grid = Grid::from_base_10(ordinal);
+            assert_eq!(
+                grid.outcome(),
+                reference_outcome(reference_fields(ordinal)),
+                "ordinal {}",
+                ordinal
+            );
+        }
+    }
+
+    #[test]
+    fn put_and_empty_fields_agree_with_fields() {
+        for ordinal in 0..3usize.pow(9) {
+            let grid = Grid::from_base_10(ordinal);
+            let empty: Vec<_> = (0..9)
+                .filter(|field| grid.field(*field) == Field::Empty)
+                .collect();
+            assert_eq!(grid.empty_fields(), empty);
+            for field in empty {
+                let marked = grid.put(field, Player::X);
+                assert_eq!(marked.field(field), Field::X);
+                assert_eq!(marked.with_field(field, Field::Empty), grid);
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Values of grids approximated by a weighted sum of features of the grid,
+//! instead of a table with a value for each grid. Grids which share features
+//! share what is learned about them, so the weights generalize to grids the
+//! agent has never seen, and their number doesn't grow with the board.
+
+use super::semi_gradient::{Differentiable, SemiGradientLearner};
+use super::{Grid, Player, ValueFunction, LINES};
+
+/// How many features describe a grid.
+pub const FEATURES: usize = 15;
+
+/// The names of the features, in the order `features` lists them.
+pub const FEATURE_NAMES: [&str; FEATURES] = [
+    "bias",
+    "own lines with one mark",
+    "own lines with two marks",
+    "opponent lines with one mark",
+    "opponent lines with two marks",
+    "own forks",
+    "opponent forks",
+    "own center",
+    "opponent center",
+    "own corners",
+    "opponent corners",
+    "own edges",
+    "opponent edges",
+    "mark difference",
+    "empty fields",
+];
+
+const CENTER: u16 = 1 << 4;
+const CORNERS: u16 = 0b101_000_101;
+const EDGES: u16 = 0b010_101_010;
+
+/// Weights of the features of a grid from the perspective of the player they
+/// were learned for. The value of a grid where the game goes on is the sum of
+/// its features multiplied by the weights. Finished games are valued exactly,
+/// like in the table: 1.0 for a win and 0.0 for a draw or a loss.
+#[derive(Clone, Debug)]
+pub struct LinearValues {
+    /// The player the values are of.
+    pub player: Player,
+    /// One weight for each feature.
+    pub weights: [f32; FEATURES],
+}
+
+/// Learns the weights of the features by semi-gradient TD(λ). The gradient of
+/// a linear value with respect to the weights is the vector of features, so
+/// each temporal difference moves every weight in proportion to its feature.
+pub type LinearLearner = SemiGradientLearner<LinearValues>;
+
+/// Describes the grid from the perspective of given player. An open line has
+/// marks of one player only. A fork is an empty field which would give the
+/// player two open lines with two marks at once. The counts are scaled to be
+/// at most about 1, so that no feature dominates the updates.
+pub fn features(grid: Grid, player: Player) -> [f32; FEATURES] {
+    let own = grid.marks(player);
+    let opponent = grid.marks(player.opponent());
+    let empty = grid.empty_fields();
+
+    // How many open lines of the player have given number of marks.
+    let open_lines = |marks: u16, others: u16, count: u32| {
+        LINES
+            .iter()
+            .filter(|line| {
+                *line & others == 0 && (*line & marks).count_ones() == count
+            })
+            .count() as f32
+    };
+    let forks = |marks: u16, others: u16| {
+        empty
+            .iter()
+            .filter(|field| {
+                let with_field = marks | 1 << **field;
+                LINES
+                    .iter()
+                    .filter(|line| {
+                        *line & 1 << **field != 0
+                            && *line & others == 0
+                            && (*line & with_field).count_ones() == 2
+                    })
+                    .count()
+                    >= 2
+            })
+            .count() as f32
+    };
+    let occupied = |marks: u16, fields: u16| (marks & fields).count_ones();
+
+    [
+        1.0,
+        open_lines(own, opponent, 1) / 8.0,
+        open_lines(own, opponent, 2) / 8.0,
+        open_lines(opponent, own, 1) / 8.0,
+        open_lines(opponent, own, 2) / 8.0,
+        forks(own, opponent) / 9.0,
+        forks(opponent, own) / 9.0,
+        occupied(own, CENTER) as f32,
+        occupied(opponent, CENTER) as f32,
+        occupied(own, CORNERS) as f32 / 4.0,
+        occupied(opponent, CORNERS) as f32 / 4.0,
+        occupied(own, EDGES) as f32 / 4.0,
+        occupied(opponent, EDGES) as f32 / 4.0,
+        own.count_ones() as f32 - opponent.count_ones() as f32,
+        empty.len() as f32 / 9.0,
+    ]
+}
+
+impl LinearValues {
+    /// Weights which value each grid where the game goes on at 0.5, like the
+    /// initial table does.
+    pub fn new(player: Player) -> Self {
+        let mut weights = [0.0; FEATURES];
+        weights[0] = 0.5;
+        Self { player, weights }
+    }
+
+    /// The weighted sum of the features.
+    pub fn estimate(&self, features: &[f32; FEATURES]) -> f32 {
+        self.weights.iter().zip(features).map(|(w, x)| w * x).sum()
+    }
+
+    /// Prints each feature with its weight.
+    pub fn print(&self) {
+        for (name, weight) in FEATURE_NAMES.iter().zip(&self.weights) {
+            println!("{:>30} {:>8.3}", name, weight);
+        }
+    }
+}
+
+impl ValueFunction for LinearValues {
+    fn value(&self, grid: Grid) -> f32 {
+        if grid.outcome().is_over() {
+            (grid.outcome().winner() == Some(self.player)) as u8 as f32
+        } else {
+            self.estimate(&features(grid, self.player))
+        }
+    }
+}
+
+impl Differentiable for LinearValues {
+    fn gradient(&self, grid: Grid) -> (f32, Vec<f32>) {
+        let features = features(grid, self.player);
+        (self.estimate(&features), features.to_vec())
+    }
+
+    fn weights_mut(&mut self) -> &mut [f32] {
+        &mut self.weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learner::Hyperparameters;
+    use crate::policies::Policy;
+    use crate::SeededRng;
+    use rand::SeedableRng;
+
+    /// X . .
+    /// . X .
+    /// . . O
+    fn grid() -> Grid {
+        Grid::new()
+            .put(0, Player::X)
+            .put(8, Player::O)
+            .put(4, Player::X)
+    }
+
+    #[test]
+    fn features_of_x() {
+        // X has five open lines with one mark and can fork in 1, 2, 3 and 6.
+        // O has two open lines, the bottom row and the right column.
+        assert_eq!(
+            features(grid(), Player::X),
+            [
+                1.0,
+                5.0 / 8.0,
+                0.0,
+                2.0 / 8.0,
+                0.0,
+                4.0 / 9.0,
+                0.0,
+                1.0,
+                0.0,
+                1.0 / 4.0,
+                1.0 / 4.0,
+                0.0,
+                0.0,
+                1.0,
+                6.0 / 9.0,
+            ]
+        );
+    }
+
+    #[test]
+    fn features_of_o() {
+        assert_eq!(
+            features(grid(), Player::O),
+            [
+                1.0,
+                2.0 / 8.0,
+                0.0,
+                5.0 / 8.0,
+                0.0,
+                0.0,
+                4.0 / 9.0,
+                0.0,
+                1.0,
+                1.0 / 4.0,
+                1.0 / 4.0,
+                0.0,
+                0.0,
+                -1.0,
+                6.0 / 9.0,
+            ]
+        );
+    }
+
+    #[test]
+    fn loss_moves_estimate_towards_zero() {
+        let mut rng = SeededRng::seed_from_u64(0);
+        let mut learner = LinearLearner::new(
+            LinearValues::new(Player::X),
+            Hyperparameters {
+                exploration_probability: 0.0,
+                ..Hyperparameters::default()
+            },
+        );
+        let grid = Grid::new();
+        let field =
+            learner.pick(&mut rng, grid, Player::X, &grid.empty_fields());
+        let afterstate = grid.put(field, Player::X);
+        let before = learner.values.value(afterstate);
+
+        // O wins the middle row, whatever X marked.
+        let lost = Grid::new()
+            .put(3, Player::O)
+            .put(4, Player::O)
+            .put(5, Player::O);
+        assert_eq!(learner.values.value(lost), 0.0);
+        learner.game_over(lost, Player::X);
+
+        let after = learner.values.value(afterstate);
+        assert!(
+            0.0 < after && after < before,
+            "{} moved to {} rather than towards 0",
+            before,
+            after
+        );
+    }
+}
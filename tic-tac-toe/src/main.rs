@@ -1,373 +1,583 @@
-mod num_ext;
-mod policies;
+mod cli;
+mod human;
 
-use num_ext::*;
+use cli::{Args, Command, Opponent};
 use rand::prelude::*;
 use std::env;
-use std::fmt;
+use std::fs;
+use std::io;
+use std::process;
+use tic_tac_toe::learner::{Algorithm, Greedy, Hyperparameters, Learner};
+use tic_tac_toe::mcts::{Guidance, Mcts};
+use tic_tac_toe::minimax::Minimax;
+use tic_tac_toe::network::NetworkLearner;
+use tic_tac_toe::policies::{AnySide, Policy};
+use tic_tac_toe::record::{self, Recorder};
+use tic_tac_toe::{
+    analysis, evaluation, exact, storage, tournament, training, Indexing,
+    Player, SeededRng,
+};
 
-/// Dictates how often an exploration move happens. Exploration move means that
-/// given a list of allowed actions, one is selected at random rather than one
-/// with the highest reward.
-const EXPLORATION_PROBABILITY: f32 = 0.01;
-
-/// Akin to learning rate. Step size is a fraction which will bound the temporal
-/// difference in value between state `s` and `s'`.
-const STEP_SIZE: f32 = 0.2;
-
-/// Tic-tac-toe is played on 3x3 grid. Since there are 9 fields and each field
-/// can be in 3 states, there are 3^9 = 19683 distinct grids.
-///
-/// Grid is sometimes referred to more generally as state.
-#[derive(Clone, Copy, Debug)]
-struct Grid {
-    pub fields: [Field; 9],
-}
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return;
+    }
 
-/// Each tic-tac-toe grid field can either have an X, an O or be empty.
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Field {
-    Empty,
-    O,
-    X,
+    let seed = args.seed.unwrap_or_else(|| thread_rng().gen());
+    // Only runs which depend on the seed print it, so that the output of the
+    // others stays the same from run to run.
+    if args.uses_rng() {
+        println!("Seed {}.", seed);
+    }
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let mut recorder = args.record.as_ref().map(|path| {
+        Recorder::create(path, seed, args.record_every)
+            .unwrap_or_else(|e| exit_with_error(&e.to_string()))
+    });
+
+    match args.command {
+        Command::CompareSymmetry => {
+            let games = args.games.unwrap_or(100_000);
+            training::compare_symmetry(seed, games as usize);
+        }
+        Command::CompareAlgorithms => {
+            let games = args.games.unwrap_or(100_000);
+            let indexing = args.indexing.unwrap_or(Indexing::Ternary);
+            let hyperparameters = hyperparameters(&args, None);
+            training::compare_algorithms(
+                seed,
+                games as usize,
+                indexing,
+                hyperparameters,
+            );
+        }
+        Command::Export => export(&args),
+        Command::Replay => replay(&args),
+        Command::Tournament => tournament(&mut rng, &args, recorder.as_mut()),
+        Command::Train
+        | Command::SelfPlay
+        | Command::Play
+        | Command::Eval
+        | Command::Analyze
+        | Command::Report => run(&mut rng, &args, recorder.as_mut()),
+    }
 }
 
-/// The two players.
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Player {
-    X,
-    O,
+/// Saves the values or the network of the learner.
+fn save_learner(path: &str, header: &storage::Header, learner: &dyn Learner) {
+    match learner.network() {
+        Some(network) => storage::save_network(path, header, network),
+        None => {
+            let values = learner.values().expect("Learner has no values");
+            storage::save(path, header, values)
+        }
+    }
+    .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    println!("Saved values to {}.", path);
 }
 
-/// Value vector holds a ranking (how favorable it is) for each grid. Since
-/// there are 19683 distinct states that the grid can be in, this is also the
-/// length of the vector. The position of each grid in the vector is given by
-/// converting it from radix 3 to radix 10.
-type Values = Vec<f32>;
-
-impl Grid {
-    /// Creates a new state where each field is set to empty.
-    fn new() -> Self {
-        Self {
-            fields: [Field::Empty; 9],
+/// Trains the agent and then saves it, evaluates it, analyzes a board or plays
+/// against a human, depending on the command. The recorder records the games
+/// against the human and a sample of the training games.
+fn run(rng: &mut SeededRng, args: &Args, mut recorder: Option<&mut Recorder>) {
+    // The board is checked before any training, which may take a while.
+    let board = match (args.command, &args.board) {
+        (Command::Analyze, Some(board)) => Some(
+            analysis::parse_board(board, args.first.unwrap_or(Player::X))
+                .unwrap_or_else(|e| exit_with_error(&e)),
+        ),
+        (Command::Analyze, None) => exit_with_error("Analyze needs a board"),
+        _ => None,
+    };
+
+    let trains = matches!(args.command, Command::Train | Command::SelfPlay);
+    // Training continues from `--from` and saves into `--model`, the other
+    // commands load the agent from `--model`.
+    let (load, save) = if trains {
+        (&args.from, &args.model)
+    } else {
+        (&args.model, &None)
+    };
+    // A loaded network is learned by the network algorithm unless another
+    // one is asked for, which then fails to load it.
+    let algorithm = args.algorithm.unwrap_or_else(|| match load {
+        Some(path) if storage::is_network(path).unwrap_or(false) => {
+            Algorithm::Network
         }
+        _ => Algorithm::Afterstate,
+    });
+    let stores = algorithm.learns_values() || algorithm == Algorithm::Network;
+    if !stores
+        && (load.is_some() || save.is_some() || args.second_model.is_some())
+    {
+        exit_with_error(
+            "Only tables of values of grids and networks can be saved and \
+             loaded",
+        );
     }
 
-    /// Puts given player's mark on given field.
-    /// ```text
-    ///  0 | 1 | 2
-    /// ---+---+---
-    ///  3 | 4 | 5
-    /// ---+---+---
-    ///  6 | 7 | 8
-    /// ```
-    fn put(mut self, field_index: usize, player: Player) -> Self {
-        debug_assert!(field_index < 9);
-        self.fields[field_index] = player.into();
-        self
-    }
+    let (mut header, mut learner) = match load {
+        Some(path) if algorithm == Algorithm::Network => {
+            let (header, network) = storage::load_network(path)
+                .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+            println!(
+                "Loaded a network of {:?} trained on {} games.",
+                header.player, header.training_games
+            );
+            let hyperparameters = hyperparameters(args, Some(&header));
+            let learner: Box<dyn Learner> =
+                Box::new(NetworkLearner::new(network, hyperparameters));
+            (header, learner)
+        }
+        Some(path) => {
+            let (header, mut values) = storage::load(path)
+                .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+            println!(
+                "Loaded values of {:?} trained on {} games.",
+                header.player, header.training_games
+            );
+            if let Some(indexing) = args.indexing {
+                values = values.reindex(indexing, header.player);
+            }
+            let hyperparameters = hyperparameters(args, Some(&header));
+            let learner = algorithm
+                .learner_with_values(values, hyperparameters)
+                .expect("The algorithm learns values");
+            (header, learner)
+        }
+        None => {
+            let player = args.side.unwrap_or(Player::X);
+            let hyperparameters = hyperparameters(args, None);
+            let header = storage::Header {
+                player,
+                step_size: hyperparameters.step_size,
+                exploration_probability: hyperparameters
+                    .exploration_probability,
+                training_games: 0,
+            };
+            let indexing = args.indexing.unwrap_or(Indexing::Ternary);
+            let learner =
+                algorithm.learner(rng, player, indexing, hyperparameters);
+            (header, learner)
+        }
+    };
 
-    /// Prints the grid into console.
-    fn print(self) {
-        let s = self.fields;
-        println!(" {} | {} | {} ", s[0], s[1], s[2]);
-        println!("---+---+---");
-        println!(" {} | {} | {} ", s[3], s[4], s[5]);
-        println!("---+---+---");
-        println!(" {} | {} | {} ", s[6], s[7], s[8]);
+    if args.command == Command::Report && learner.value_function().is_none() {
+        exit_with_error("Only values of grids can be compared, not of actions");
     }
 
-    /// Returns `None` if the game is not over, returns `Some(true)` if the
-    /// provided player has won and `Some(false)` if the provided player hasn't
-    /// won.
-    ///
-    /// Winning is of course determined by having 3 in a row (either vertically,
-    /// horizontally or diagonally).
-    fn has_won(self, player: Player) -> Option<bool> {
-        // If we assign each field a numerical value as follows, we have to
-        // check the middle field (4) in both diagonals, the row and the column.
-        // Then we have to check the row and the column at the first field (0)
-        // and at the last field (8).
-        //
-        // ```text
-        //  0 | 1 | 2
-        // ---+---+---
-        //  3 | 4 | 5
-        // ---+---+---
-        //  6 | 7 | 8
-        // ```
-        let s = self.fields;
-
-        // Does the player have 3 in a row which involves the middle field?
-        let connected_via_middle = || {
-            (s[4] == s[1] && s[4] == s[7])
-                || (s[4] == s[3] && s[4] == s[5])
-                || (s[4] == s[0] && s[4] == s[8])
-                || (s[4] == s[6] && s[4] == s[2])
+    // Loaded agents are only trained further if asked to.
+    let training_games =
+        args.games
+            .unwrap_or(if load.is_some() && !trains { 0 } else { 1000 });
+    // The values are always of the player they were trained for. The agent
+    // plays the other side by seeing the grid with the marks swapped.
+    let player = header.player;
+    let schedule = training::Schedule {
+        side: args.side.unwrap_or(player),
+        first: args.first.unwrap_or(Player::X),
+        alternate: args.alternate,
+    };
+    let indexing = learner
+        .values()
+        .map(|values| values.indexing)
+        .or(args.indexing)
+        .unwrap_or(Indexing::Ternary);
+    let hyperparameters = hyperparameters(args, Some(&header));
+    let mut agent = AnySide {
+        policy: &mut learner,
+        side: player,
+    };
+
+    if args.command == Command::SelfPlay {
+        println!("Playing {} self-play games.", training_games);
+
+        let mut second = algorithm.learner(
+            rng,
+            player.opponent(),
+            indexing,
+            hyperparameters,
+        );
+        let mut opponent = AnySide {
+            policy: &mut second,
+            side: player.opponent(),
         };
-
-        // That can only be the case if the middle field holds player's mark.
-        if s[4] == player && connected_via_middle() {
-            return Some(true);
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.start("self-play", "agent", "second agent");
         }
-
-        // Does the player have 3 in a row in the first column or row?
-        let connected_via_first =
-            || (s[0] == s[1] && s[0] == s[2]) || (s[0] == s[3] && s[0] == s[6]);
-
-        // If they have a stone, maybe.
-        if s[0] == player && connected_via_first() {
-            return Some(true);
+        let tally = training::self_play(
+            rng,
+            &mut agent,
+            &mut opponent,
+            training_games as usize,
+            schedule,
+            recorder.as_deref_mut(),
+        )
+        .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+        println!("overall:        {}", tally);
+
+        // The second learner starts from scratch, so all it knows is from
+        // these games.
+        if let Some(path) = &args.second_model {
+            let header = storage::Header {
+                player: player.opponent(),
+                step_size: hyperparameters.step_size,
+                exploration_probability: hyperparameters
+                    .exploration_probability,
+                training_games,
+            };
+            save_learner(path, &header, &*second);
         }
-
-        // Does the player have 3 in a row in the last column or row?
-        let connected_via_last =
-            || (s[8] == s[5] && s[8] == s[2]) || (s[8] == s[7] && s[8] == s[6]);
-
-        // If they have a stone, maybe.
-        if s[8] == player && connected_via_last() {
-            return Some(true);
+    } else if training_games > 0 {
+        println!("Playing {} training games.", training_games);
+
+        // Only train takes the opponent from the arguments, eval uses it for
+        // the evaluation.
+        let opponent = match args.command {
+            Command::Train | Command::Play => args.opponent,
+            _ => None,
         }
-
-        // If there is no empty field where a mark can be put, and the player
-        // didn't win, then we return false.
-        // Otherwise the game is still on!
-        if s.iter().filter(|field| **field == Field::Empty).count() == 0 {
-            Some(false)
-        } else {
-            None
+        .unwrap_or(Opponent::Random);
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.start("train", "agent", opponent.name());
         }
+        training::train(
+            rng,
+            &mut agent,
+            &mut *opponent.policy(args.simulations),
+            training_games as usize,
+            schedule,
+            recorder.as_deref_mut(),
+        )
+        .unwrap_or_else(|e| exit_with_error(&e.to_string()));
     }
-
-    /// Calculates the position of the grid in the vector of values. It amounts
-    /// to treating each of the 9 fields of the grid as a numeral in ternary
-    /// system (base 3). Then each field state is arbitrarily assigned a value
-    /// 0, 1 or 2.
-    fn to_base_10(self) -> usize {
-        self.fields
-            .iter()
-            .zip(0..9)
-            .fold(0usize, |ordinal, (field, i)| {
-                ordinal + field.as_usize() * 3usize.pow(i)
-            })
-    }
-
-    /// Converts a decimal number into ternary system (base 3). Then from the
-    /// system creates a grid thanks to an arbitrary conversion between 0, 1, 2
-    /// and X, O, empty field.
-    fn from_base_10(ordinal: usize) -> Self {
-        let ninth = ordinal / 3usize.pow(8);
-        debug_assert!(ninth <= 2);
-        let ordinal = ordinal - ninth * 3usize.pow(8);
-        let eighth = ordinal / 3usize.pow(7);
-        debug_assert!(eighth <= 2);
-        let ordinal = ordinal - eighth * 3usize.pow(7);
-        let seventh = ordinal / 3usize.pow(6);
-        debug_assert!(seventh <= 2);
-        let ordinal = ordinal - seventh * 3usize.pow(6);
-        let sixth = ordinal / 3usize.pow(5);
-        debug_assert!(sixth <= 2);
-        let ordinal = ordinal - sixth * 3usize.pow(5);
-        let fifth = ordinal / 3usize.pow(4);
-        debug_assert!(fifth <= 2);
-        let ordinal = ordinal - fifth * 3usize.pow(4);
-        let forth = ordinal / 3usize.pow(3);
-        debug_assert!(forth <= 2);
-        let ordinal = ordinal - forth * 3usize.pow(3);
-        let third = ordinal / 3usize.pow(2);
-        debug_assert!(third <= 2);
-        let ordinal = ordinal - third * 3usize.pow(2);
-        let second = ordinal / 3usize;
-        debug_assert!(second <= 2);
-        let ordinal = ordinal - second * 3usize;
-        let first = ordinal;
-        debug_assert!(first <= 2);
-        Self {
-            fields: [
-                Field::from_usize(first),
-                Field::from_usize(second),
-                Field::from_usize(third),
-                Field::from_usize(forth),
-                Field::from_usize(fifth),
-                Field::from_usize(sixth),
-                Field::from_usize(seventh),
-                Field::from_usize(eighth),
-                Field::from_usize(ninth),
-            ],
+    header.training_games += training_games;
+    header.step_size = hyperparameters.step_size;
+    header.exploration_probability = hyperparameters.exploration_probability;
+
+    match args.command {
+        Command::Train | Command::SelfPlay => {
+            if let Some(values) = learner.values() {
+                println!("Visits of the grids the agent moved into:");
+                training::report_visits(values);
+            }
+            if let Some(path) = save {
+                save_learner(path, &header, &*learner);
+            }
+        }
+        Command::Eval => {
+            let sides: &[Player] = if args.alternate {
+                &[Player::X, Player::O]
+            } else {
+                &[schedule.side]
+            };
+            for side in sides {
+                let mut greedy = learner.greedy();
+                let mut greedy = AnySide {
+                    policy: &mut greedy,
+                    side: player,
+                };
+                let mut search = args
+                    .search
+                    .map(|guidance| search(args, &*learner, guidance, player));
+                let agent: &mut dyn Policy = match &mut search {
+                    Some(search) => search,
+                    None => &mut greedy,
+                };
+                match args.opponent.unwrap_or(Opponent::Minimax) {
+                    Opponent::Minimax => {
+                        evaluation::against_minimax(
+                            rng,
+                            agent,
+                            *side,
+                            schedule.first,
+                        );
+                    }
+                    Opponent::Random => {
+                        let odds = evaluation::against_random(
+                            rng,
+                            agent,
+                            *side,
+                            schedule.first,
+                        );
+                        println!(
+                            "{:?} against a random player: wins {:.1}%  \
+                             draws {:.1}%  losses {:.1}%",
+                            side,
+                            100.0 * odds.win,
+                            100.0 * odds.draw,
+                            100.0 * odds.loss
+                        );
+                    }
+                    Opponent::Mcts => {
+                        let tally = evaluation::against(
+                            rng,
+                            agent,
+                            &mut Mcts::new(args.simulations),
+                            *side,
+                            schedule.first,
+                        );
+                        println!(
+                            "{:?} against tree search: wins {}  draws {}  \
+                             losses {}",
+                            side,
+                            tally.wins(*side),
+                            tally.draws,
+                            tally.losses(*side)
+                        );
+                    }
+                }
+            }
+        }
+        Command::Report => {
+            let values =
+                learner.value_function().expect("The learner has values");
+            let report = exact::compute(values, player, schedule.first);
+            print!("\n{}", report.to_text());
+            if let Some(path) = &args.output {
+                fs::write(path, report.to_csv())
+                    .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+                println!("Saved the report to {}.", path);
+            }
+        }
+        Command::Analyze => {
+            let (grid, to_move) = board.expect("The board was parsed");
+            println!();
+            grid.print();
+            println!();
+            analysis::analyze(
+                grid,
+                to_move,
+                learner.value_function().map(|values| (values, player)),
+                &mut Minimax::new(),
+            );
+        }
+        _ => {
+            // The human plays the other side. Since moves can be taken back,
+            // the agent plays its best moves and doesn't learn from the games.
+            let stdin = io::stdin();
+            let mut input = stdin.lock();
+            let mut greedy = learner.greedy();
+            let mut greedy = AnySide {
+                policy: &mut greedy,
+                side: player,
+            };
+            let mut search = args
+                .search
+                .map(|guidance| search(args, &*learner, guidance, player));
+            let agent: &mut dyn Policy = match &mut search {
+                Some(search) => search,
+                None => &mut greedy,
+            };
+            let human = schedule.side.opponent();
+            let values =
+                learner.value_function().map(|values| (values, player));
+            if let Some(recorder) = recorder.as_deref_mut() {
+                recorder.start("play", "agent", "human");
+            }
+            let mut game = 0;
+            while let human::Ending::Finished { moves, result } = human::play(
+                rng,
+                &mut input,
+                agent,
+                values,
+                human,
+                schedule.first,
+            ) {
+                game += 1;
+                if let Some(recorder) = recorder.as_deref_mut() {
+                    recorder
+                        .record(
+                            game,
+                            schedule.side,
+                            schedule.first,
+                            moves,
+                            result,
+                        )
+                        .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+                }
+            }
         }
     }
 }
 
-impl PartialEq<Player> for Field {
-    /// Does the player own the field's mark?
-    fn eq(&self, player: &Player) -> bool {
-        match self {
-            Self::O => *player == Player::O,
-            Self::X => *player == Player::X,
-            Self::Empty => false,
-        }
+/// Converts the values in the model file to another format or indexing. A
+/// network is only converted to another format.
+fn export(args: &Args) {
+    let (Some(model), Some(output)) = (&args.model, &args.output) else {
+        exit_with_error("Export needs both --model and --output");
+    };
+    if storage::is_network(model)
+        .unwrap_or_else(|e| exit_with_error(&e.to_string()))
+    {
+        let (header, network) = storage::load_network(model)
+            .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+        storage::save_network(output, &header, &network)
+            .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+        println!("Exported the network to {}.", output);
+        return;
     }
+    let (header, mut values) = storage::load(model)
+        .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    if let Some(indexing) = args.indexing {
+        values = values.reindex(indexing, header.player);
+    }
+    storage::save(output, &header, &values)
+        .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    println!("Exported values to {}.", output);
 }
 
-impl From<Player> for Field {
-    fn from(player: Player) -> Self {
-        match player {
-            Player::X => Self::X,
-            Player::O => Self::O,
-        }
+/// Plays a round-robin tournament between the entrants and prints the results.
+/// The entrants are policies or model files whose agents play greedily.
+fn tournament(
+    rng: &mut SeededRng,
+    args: &Args,
+    recorder: Option<&mut Recorder>,
+) {
+    if args.entrants.len() < 2 {
+        exit_with_error("Tournament needs at least two entrants");
     }
-}
 
-impl fmt::Display for Field {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::X => write!(f, "X"),
-            Self::O => write!(f, "O"),
-            Self::Empty => write!(f, " "),
-        }
+    // Values are loaded first, since the greedy agents borrow them.
+    let models: Vec<_> = args
+        .entrants
+        .iter()
+        .filter(|entrant| entrant.parse::<Opponent>().is_err())
+        .map(|path| {
+            let (header, values) = storage::load_value_function(path)
+                .unwrap_or_else(|e| {
+                    exit_with_error(&format!("Cannot load {}: {}", path, e))
+                });
+            (header.player, values)
+        })
+        .collect();
+    let mut greedies: Vec<_> = models
+        .iter()
+        .map(|(player, values)| (*player, Greedy { values: &**values }))
+        .collect();
+    let mut greedies = greedies.iter_mut();
+    let mut entrants: Vec<(String, Box<dyn Policy + '_>)> = args
+        .entrants
+        .iter()
+        .map(|entrant| {
+            let policy: Box<dyn Policy + '_> = match entrant.parse::<Opponent>()
+            {
+                Ok(opponent) => opponent.policy(args.simulations),
+                Err(_) => {
+                    let (player, greedy) =
+                        greedies.next().expect("Each model was loaded");
+                    Box::new(AnySide {
+                        policy: greedy,
+                        side: *player,
+                    })
+                }
+            };
+            (entrant.clone(), policy)
+        })
+        .collect();
+
+    let games = args.games.unwrap_or(100) as usize;
+    println!(
+        "Playing {} games on each side between each pair of {} entrants.",
+        games,
+        entrants.len()
+    );
+    let standings = tournament::play(
+        rng,
+        &mut entrants,
+        games,
+        args.first.unwrap_or(Player::X),
+        recorder,
+    )
+    .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    print!("\n{}", standings.to_text());
+    if let Some(path) = &args.output {
+        fs::write(path, standings.to_csv())
+            .unwrap_or_else(|e| exit_with_error(&e.to_string()));
+        println!("Saved the results to {}.", path);
     }
 }
 
-impl Field {
-    /// Arbitrary conversion of `Field` into `usize`. Must match the
-    /// `Field::from_usize` method.
-    fn as_usize(self) -> usize {
-        match self {
-            Self::Empty => 0,
-            Self::O => 1,
-            Self::X => 2,
-        }
+/// Shows the recorded games move by move, with the values of the agent in the
+/// model file if one is given.
+fn replay(args: &Args) {
+    let Some(path) = &args.replay else {
+        exit_with_error("Replay needs a file with recorded games");
+    };
+    let records = record::load(path).unwrap_or_else(|e| exit_with_error(&e));
+    let model = args.model.as_ref().map(|model| {
+        storage::load_value_function(model)
+            .unwrap_or_else(|e| exit_with_error(&e.to_string()))
+    });
+    let values = model
+        .as_ref()
+        .map(|(header, values)| (&**values, header.player));
+
+    let records: Vec<_> = records
+        .iter()
+        .filter(|record| args.game.map_or(true, |game| record.game == game))
+        .collect();
+    if records.is_empty() {
+        exit_with_error("There is no such game in the file");
     }
-
-    /// Arbitrary conversion of `usize` into `Field`. Must match the
-    /// `Field::as_usize` method.
-    fn from_usize(u: usize) -> Self {
-        match u {
-            0 => Self::Empty,
-            1 => Self::O,
-            2 => Self::X,
-            _ => panic!("Field can only be created from 0, 1 or 2."),
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    for record in records {
+        if !record::replay(record, values, &mut input) {
+            break;
         }
     }
 }
 
-/// Creates a vector with initial values for each state. Each state which at
-/// least one empty field is rated 0.5. Each state without a winner or where
-/// an opponent has won is set to 0.0. Each state where given player won is
-/// set to 1.0.
-fn initial_values(player: Player) -> Values {
-    // There are 3^9 possible states in the game of tic tac toe because each of
-    // the 9 fields can be in 3 states.
-    let possible_states = 3usize.pow(9);
-    (0..possible_states)
-        .map(|ordinal| {
-            Grid::from_base_10(ordinal)
-                .has_won(player)
-                .map(|has_player_won| if has_player_won { 1.0 } else { 0.0 })
-                .unwrap_or(0.5)
-        })
-        .collect()
+/// The hyperparameters given in the arguments. The step size and the
+/// exploration probability which aren't given are those of the loaded values,
+/// if any, or the defaults.
+fn hyperparameters(
+    args: &Args,
+    header: Option<&storage::Header>,
+) -> Hyperparameters {
+    let default = Hyperparameters::default();
+    Hyperparameters {
+        step_size: args
+            .step_size
+            .or(header.map(|header| header.step_size))
+            .unwrap_or(default.step_size),
+        exploration_probability: args
+            .exploration_probability
+            .or(header.map(|header| header.exploration_probability))
+            .unwrap_or(default.exploration_probability),
+        ..args.hyperparameters
+    }
 }
 
-/// Plays one game against given policy. Updates the value vector during the
-/// game. It'd be nicer if the policy was trait and it could be combined in a
-/// way where we could have two actors with their own set of state values play
-/// against each other. Although this is easy to do, tic tac toe is not
-/// interesting enough.
-fn play_game(
-    rng: &mut ThreadRng,
-    values: &mut Values,
-    mut policy: impl FnMut(&'_ mut ThreadRng, Grid, &mut Vec<usize>) -> usize,
-) -> Grid {
-    let mut grid = Grid::new();
-
-    // This is a bit awkward but. It associates each action (vector index) with
-    // a field on the grid (the value).
-    let mut possible_actions: Vec<_> = (0..9).collect();
-    loop {
-        // --- Actor's move. Actor plays Xs. ---
-
-        // Rolls a dice whether it should do an exploratory move.
-        let should_explore = rng.gen_range(0.0, 1.0);
-        if should_explore <= EXPLORATION_PROBABILITY {
-            // Pick a random action from the set of possible actions.
-            let action = rng.gen_range(0, possible_actions.len());
-            // Gets the field which the action represents and marks it as X.
-            let field_to_mark = possible_actions.swap_remove(action);
-            grid.fields[field_to_mark] = Field::X;
-        } else {
-            let (action, next_state_value) = possible_actions
-                .iter()
-                .enumerate()
-                .map(|(action, field_to_mark)| {
-                    let grid_after_action = grid.put(*field_to_mark, Player::X);
-                    let state_value = values[grid_after_action.to_base_10()];
-                    (action, state_value)
-                })
-                .max_by(|(_, value), (_, another_value)| {
-                    (*value).partial_ord(*another_value)
-                })
-                .expect("There must be at least one action to take");
-
-            // Updates the value of the state to be closed to the next state by
-            // using the temporal difference.
-            let state_ordinal = grid.to_base_10();
-            values[state_ordinal] +=
-                STEP_SIZE * (next_state_value - values[state_ordinal]);
-
-            // Field that should be marked as X.
-            let field_to_mark = possible_actions.swap_remove(action);
-            debug_assert_eq!(Field::Empty, grid.fields[field_to_mark]);
-            grid.fields[field_to_mark] = Field::X;
-        }
-
-        if let Some(true) = grid.has_won(Player::X) {
-            // TODO: Would it make sense to set the previous state value to 1?
-            break;
-        }
-
-        if possible_actions.is_empty() {
-            // TODO: Would it make sense to set the previous state value to 0?
-            break;
-        }
-
-        // --- Policy move. For example human. ---
-        let state_ordinal = grid.to_base_10();
-        let field_to_mark = policy(rng, grid, &mut possible_actions);
-        debug_assert_eq!(Field::Empty, grid.fields[field_to_mark]);
-        grid.fields[field_to_mark] = Field::O;
-        if let Some(true) = grid.has_won(Player::O) {
-            values[state_ordinal] = 0.0;
-            break;
+/// Tree search which picks the moves of the agent, guided by the values it
+/// learned for given player.
+fn search<'a>(
+    args: &Args,
+    learner: &'a dyn Learner,
+    guidance: Guidance,
+    player: Player,
+) -> Mcts<'a> {
+    match (guidance, learner.value_function()) {
+        (Guidance::None, _) => Mcts::new(args.simulations),
+        (_, Some(values)) => {
+            Mcts::with_values(args.simulations, guidance, values, player)
         }
+        (_, None) => exit_with_error(
+            "Only values of grids can guide the search, not of actions",
+        ),
     }
-
-    grid
 }
 
-fn main() {
-    let mut rng = thread_rng();
-    let mut values = initial_values(Player::X);
-
-    // How many games should the agent play against a random policy to train its
-    // value vector.
-    let mut args = env::args();
-    args.next();
-    let training_games =
-        args.next().and_then(|s| s.parse().ok()).unwrap_or(1000);
-
-    println!("Playing {} training games.", training_games);
-
-    // Trains the actor against a random policy.
-    for _ in 0..training_games {
-        play_game(&mut rng, &mut values, policies::random);
-    }
-
-    loop {
-        println!("\nNew game!");
-        let end_state = play_game(&mut rng, &mut values, policies::human);
-        println!();
-        end_state.print();
-        println!("\nGame finished.");
-    }
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
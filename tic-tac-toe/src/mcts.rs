@@ -0,0 +1,329 @@
+//! Monte Carlo tree search. Before each move, the policy grows a tree of the
+//! games which can follow from the current grid. Each simulation walks down
+//! the tree by the UCT rule, adds one new grid to it, estimates how good the
+//! grid is and propagates the estimate back up the tree.
+
+use super::num_ext::*;
+use super::policies::Policy;
+use super::{GameOutcome, Grid, Player, SeededRng, ValueFunction};
+use rand::prelude::*;
+use std::str::FromStr;
+
+/// Balances trying the moves which look best against trying the moves which
+/// were tried the least. The usual choice is √2.
+const UCT_EXPLORATION: f32 = 1.4;
+
+/// How often a rollout guided by values plays a random move instead of the
+/// best valued one.
+const ROLLOUT_EXPLORATION: f32 = 0.2;
+
+/// How the learned values take part in the search, if at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Guidance {
+    /// Grids are estimated by rollouts with random moves.
+    None,
+    /// Grids are estimated by their learned values, rescaled so that draws
+    /// count half a win like in the rest of the search. There are no
+    /// rollouts.
+    Leaf,
+    /// Grids are estimated by rollouts in which the moves are mostly the best
+    /// valued ones.
+    Rollout,
+}
+
+/// Searches the game tree with a given number of simulations per move and
+/// marks the field which was simulated the most.
+pub struct Mcts<'a> {
+    simulations: u32,
+    guidance: Guidance,
+    /// The values and the player they were learned for. Grids of the other
+    /// player are looked up with the marks swapped.
+    values: Option<(&'a dyn ValueFunction, Player)>,
+}
+
+/// A grid in the search tree.
+struct Node {
+    grid: Grid,
+    /// The player who is to move in the grid.
+    to_move: Player,
+    parent: Option<usize>,
+    /// The field marked to get to each child and its position in the tree.
+    children: Vec<(usize, usize)>,
+    /// Fields which don't have a child yet.
+    untried: Vec<usize>,
+    visits: u32,
+    /// Sum of the results from the perspective of the player who moved into
+    /// the grid: 1 for a win, 0.5 for a draw and 0 for a loss.
+    total: f32,
+}
+
+impl<'a> Mcts<'a> {
+    /// Plain search with random rollouts.
+    pub fn new(simulations: u32) -> Self {
+        Self {
+            simulations,
+            guidance: Guidance::None,
+            values: None,
+        }
+    }
+
+    /// Search guided by the values learned for given player.
+    pub fn with_values(
+        simulations: u32,
+        guidance: Guidance,
+        values: &'a dyn ValueFunction,
+        player: Player,
+    ) -> Self {
+        Self {
+            simulations,
+            guidance,
+            values: Some((values, player)),
+        }
+    }
+
+    /// How good is the grid for the player who just moved into it, between
+    /// 0 and 1.
+    fn value(&self, grid: Grid, mover: Player) -> f32 {
+        let (values, player) = self.values.expect("Guided search needs values");
+        values.afterstate_value(grid, mover, player)
+    }
+
+    /// The learned values are probabilities of winning in which a draw counts
+    /// the same as a loss, while the search scores a draw 0.5. On its scale,
+    /// the grid is worth the probability of winning plus half the probability
+    /// of a draw, i.e. 0.5 plus half the difference between the probabilities
+    /// of winning and losing. The probability of losing is the value of the
+    /// opponent's best reply for the opponent.
+    fn leaf_value(&self, grid: Grid, mover: Player) -> f32 {
+        let opponent = mover.opponent();
+        let loss = grid
+            .empty_fields()
+            .iter()
+            .map(|field| self.value(grid.put(*field, opponent), opponent))
+            .fold(0.0, f32::max);
+        0.5 + 0.5 * (self.value(grid, mover) - loss)
+    }
+
+    /// Estimates the grid which the mover just moved into.
+    fn estimate(&self, rng: &mut SeededRng, grid: Grid, mover: Player) -> f32 {
+        if let Some(result) = result(grid, mover) {
+            return result;
+        }
+
+        match self.guidance {
+            Guidance::Leaf => self.leaf_value(grid, mover),
+            Guidance::None | Guidance::Rollout => {
+                self.rollout(rng, grid, mover)
+            }
+        }
+    }
+
+    /// Plays the game out from the grid and returns the result from the
+    /// perspective of the player who just moved.
+    fn rollout(
+        &self,
+        rng: &mut SeededRng,
+        mut grid: Grid,
+        mover: Player,
+    ) -> f32 {
+        let mut player = mover.opponent();
+        loop {
+            let actions = grid.empty_fields();
+            let guided = self.guidance == Guidance::Rollout
+                && rng.gen_range(0.0, 1.0) > ROLLOUT_EXPLORATION;
+            let field = if guided {
+                *actions
+                    .iter()
+                    .max_by(|a, b| {
+                        let a = self.value(grid.put(**a, player), player);
+                        let b = self.value(grid.put(**b, player), player);
+                        a.partial_ord(b)
+                    })
+                    .expect("There must be at least one action to take")
+            } else {
+                *actions
+                    .choose(rng)
+                    .expect("There must be at least one action to take")
+            };
+
+            grid = grid.put(field, player);
+            if let Some(result) = result(grid, mover) {
+                return result;
+            }
+            player = player.opponent();
+        }
+    }
+}
+
+impl Node {
+    fn new(grid: Grid, to_move: Player, parent: Option<usize>) -> Self {
+        let untried = if grid.outcome().is_over() {
+            Vec::new()
+        } else {
+            grid.empty_fields()
+        };
+        Self {
+            grid,
+            to_move,
+            parent,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            total: 0.0,
+        }
+    }
+}
+
+impl Policy for Mcts<'_> {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        let mut tree = vec![Node::new(grid, player, None)];
+        // Only the given actions may be played from the root.
+        tree[0].untried = actions.to_vec();
+
+        for _ in 0..self.simulations {
+            // Selects a path down the tree by the UCT rule.
+            let mut node = 0;
+            while tree[node].untried.is_empty()
+                && !tree[node].children.is_empty()
+            {
+                let parent_visits = (tree[node].visits as f32).ln();
+                let uct = |child: &Node| {
+                    child.total / child.visits as f32
+                        + UCT_EXPLORATION
+                            * (parent_visits / child.visits as f32).sqrt()
+                };
+                node = tree[node]
+                    .children
+                    .iter()
+                    .map(|(_, child)| *child)
+                    .max_by(|a, b| uct(&tree[*a]).partial_ord(uct(&tree[*b])))
+                    .expect("The node has children");
+            }
+
+            // Expands the tree by one of the untried moves.
+            if !tree[node].untried.is_empty() {
+                let i = rng.gen_range(0, tree[node].untried.len());
+                let field = tree[node].untried.swap_remove(i);
+                let to_move = tree[node].to_move;
+                let child = Node::new(
+                    tree[node].grid.put(field, to_move),
+                    to_move.opponent(),
+                    Some(node),
+                );
+                tree.push(child);
+                let child = tree.len() - 1;
+                tree[node].children.push((field, child));
+                node = child;
+            }
+
+            // Propagates the estimate up, flipping the perspective with each
+            // level.
+            let mover = tree[node].to_move.opponent();
+            let mut result = self.estimate(rng, tree[node].grid, mover);
+            let mut current = Some(node);
+            while let Some(node) = current {
+                tree[node].visits += 1;
+                tree[node].total += result;
+                result = 1.0 - result;
+                current = tree[node].parent;
+            }
+        }
+
+        tree[0]
+            .children
+            .iter()
+            .max_by_key(|(_, child)| tree[*child].visits)
+            .map(|(field, _)| *field)
+            .unwrap_or_else(|| {
+                *actions
+                    .choose(rng)
+                    .expect("There must be at least one action to take")
+            })
+    }
+}
+
+/// The result of a finished game from the perspective of the player, or
+/// `None` if the game is still on.
+fn result(grid: Grid, player: Player) -> Option<f32> {
+    match grid.outcome() {
+        GameOutcome::Ongoing => None,
+        GameOutcome::Draw => Some(0.5),
+        outcome if outcome.winner() == Some(player) => Some(1.0),
+        _ => Some(0.0),
+    }
+}
+
+impl FromStr for Guidance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "leaf" => Ok(Self::Leaf),
+            "rollout" => Ok(Self::Rollout),
+            _ => Err(format!(
+                "Unknown search '{}', expected none, leaf or rollout",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_values, Indexing};
+
+    /// The field which each kind of search picks in the grid with X to move.
+    fn picks(grid: Grid) -> Vec<usize> {
+        let values = initial_values(Player::X, Indexing::Ternary);
+        let mut searches = vec![Mcts::new(1000)];
+        for guidance in [Guidance::Leaf, Guidance::Rollout] {
+            searches.push(Mcts::with_values(
+                1000,
+                guidance,
+                &values,
+                Player::X,
+            ));
+        }
+        searches
+            .iter_mut()
+            .map(|search| {
+                let mut rng = SeededRng::seed_from_u64(0);
+                search.pick(&mut rng, grid, Player::X, &grid.empty_fields())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn takes_a_win() {
+        // X X .
+        // O O .
+        // . . .
+        let grid = Grid::new()
+            .put(0, Player::X)
+            .put(3, Player::O)
+            .put(1, Player::X)
+            .put(4, Player::O);
+        assert_eq!(picks(grid), [2, 2, 2]);
+    }
+
+    #[test]
+    fn blocks_a_loss() {
+        // X . .
+        // O O .
+        // . X .
+        let grid = Grid::new()
+            .put(0, Player::X)
+            .put(3, Player::O)
+            .put(7, Player::X)
+            .put(4, Player::O);
+        assert_eq!(picks(grid), [5, 5, 5]);
+    }
+}
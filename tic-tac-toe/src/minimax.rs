@@ -0,0 +1,128 @@
+//! A perfect player which searches the whole game tree.
+
+use super::policies::Policy;
+use super::{GameOutcome, Grid, Player, SeededRng};
+use rand::prelude::*;
+
+/// Plays perfectly by searching the whole game tree. Because the tree of tic
+/// tac toe is small, the score of each grid is computed only once and then
+/// remembered.
+pub struct Minimax {
+    // Scores of grids where X is to move and where O is to move, indexed by
+    // the ordinal of the grid.
+    scores: [Vec<Option<i8>>; 2],
+}
+
+impl Minimax {
+    /// Creates the policy with no grid scored yet.
+    pub fn new() -> Self {
+        let possible_states = 3usize.pow(9);
+        Self {
+            scores: [vec![None; possible_states], vec![None; possible_states]],
+        }
+    }
+
+    /// Scores the grid from the perspective of the player who is to move.
+    /// Positive scores are wins, negative scores are losses and 0 is a draw.
+    /// The sooner the game is won, the higher the score, so that the policy
+    /// doesn't postpone wins or hurry losses.
+    ///
+    /// The grid must not be finished.
+    pub fn score(&mut self, grid: Grid, player: Player) -> i8 {
+        let ordinal = grid.to_base_10();
+        if let Some(score) = self.scores[player as usize][ordinal] {
+            return score;
+        }
+
+        let score = grid
+            .empty_fields()
+            .into_iter()
+            .map(|field| self.score_action(grid, player, field))
+            .max()
+            .expect("The grid must not be finished");
+
+        self.scores[player as usize][ordinal] = Some(score);
+        score
+    }
+
+    /// Scores marking the field from the perspective of the player.
+    pub fn score_action(
+        &mut self,
+        grid: Grid,
+        player: Player,
+        field: usize,
+    ) -> i8 {
+        let grid = grid.put(field, player);
+        match grid.outcome() {
+            GameOutcome::Ongoing => -self.score(grid, player.opponent()),
+            GameOutcome::Draw => 0,
+            // Only the player who just moved can have won. A win with more
+            // empty fields left is a faster win.
+            _ => 1 + grid.empty_fields().len() as i8,
+        }
+    }
+}
+
+impl Default for Minimax {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy for Minimax {
+    /// Picks one of the best actions at random.
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        let scores: Vec<_> = actions
+            .iter()
+            .map(|field| (*field, self.score_action(grid, player, *field)))
+            .collect();
+        let best_score = scores
+            .iter()
+            .map(|(_, score)| *score)
+            .max()
+            .expect("There must be at least one action to take");
+        let best_actions: Vec<_> = scores
+            .into_iter()
+            .filter(|(_, score)| *score == best_score)
+            .map(|(field, _)| field)
+            .collect();
+
+        *best_actions.choose(rng).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_grid_is_a_draw() {
+        let mut minimax = Minimax::new();
+        assert_eq!(minimax.score(Grid::new(), Player::X), 0);
+        assert_eq!(minimax.score(Grid::new(), Player::O), 0);
+    }
+
+    #[test]
+    fn fork_is_a_win() {
+        // X . X
+        // . O .
+        // O . X
+        // X threatens both the top row and the right column, so O can block
+        // only one of them and X wins with its next move, leaving 2 fields.
+        let grid = Grid::new()
+            .put(0, Player::X)
+            .put(4, Player::O)
+            .put(8, Player::X)
+            .put(6, Player::O)
+            .put(2, Player::X);
+        let mut minimax = Minimax::new();
+        assert_eq!(minimax.score(grid, Player::O), -3);
+        assert_eq!(minimax.score_action(grid, Player::O, 1), -3);
+    }
+}
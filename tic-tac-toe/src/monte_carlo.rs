@@ -0,0 +1,119 @@
+//! Learning the values of grids by Monte Carlo control, from the final result
+//! of each whole game.
+
+use super::exploration::Explorer;
+use super::learner::{best_action, Greedy, Hyperparameters, Learner};
+use super::policies::Policy;
+use super::{GameOutcome, Grid, Player, SeededRng, Values};
+
+/// Which visits of a state in a game count towards its value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visits {
+    /// Only the first visit of a state in a game is updated.
+    First,
+    /// Each visit of a state in a game is updated.
+    Every,
+}
+
+/// Learns the values of afterstates by Monte Carlo control. Unlike temporal
+/// difference, it doesn't bootstrap from the values of other states. It
+/// remembers the whole game, and once the game is over, moves the values of
+/// all the afterstates it visited towards the final return: 1.0 for a win and
+/// 0.0 for a draw or a loss.
+///
+/// In tic tac toe, each move adds a mark, so a state is never visited twice
+/// in the same game and both kinds of visits learn the same.
+pub struct MonteCarlo {
+    /// The values of the grids after the learner's moves.
+    pub values: Values,
+    visits: Visits,
+    hyperparameters: Hyperparameters,
+    explorer: Explorer,
+    // The grids in which the learner moved and the fields it marked in the
+    // current game, in order.
+    trajectory: Vec<(Grid, usize)>,
+}
+
+impl MonteCarlo {
+    /// Creates a learner which continues learning given values and counts
+    /// either the first or every visit of a grid in a game.
+    pub fn new(
+        values: Values,
+        visits: Visits,
+        hyperparameters: Hyperparameters,
+    ) -> Self {
+        Self {
+            values,
+            visits,
+            hyperparameters,
+            explorer: Explorer::new(
+                hyperparameters.exploration,
+                hyperparameters.exploration_probability,
+            ),
+            trajectory: Vec::new(),
+        }
+    }
+}
+
+impl Policy for MonteCarlo {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        // Exploratory moves are part of the trajectory as any other move, the
+        // values are learned for the policy which explores.
+        let values = &self.values;
+        let explored = self.explorer.explore(rng, actions, |field| {
+            let index = values.index(grid.put(field, player));
+            (values[index], index)
+        });
+        let field_to_mark = explored.unwrap_or_else(|| {
+            best_action(&self.values, grid, player, actions).0
+        });
+
+        let index = self.values.index(grid.put(field_to_mark, player));
+        self.values.visits[index] += 1;
+        self.explorer.visit(index);
+        self.trajectory.push((grid, field_to_mark));
+        field_to_mark
+    }
+
+    fn game_over(&mut self, grid: Grid, player: Player) {
+        let game_return = match grid.outcome() {
+            GameOutcome::Ongoing => panic!("The game must be over"),
+            outcome if outcome.winner() == Some(player) => 1.0,
+            _ => 0.0,
+        };
+
+        let mut visited = Vec::with_capacity(self.trajectory.len());
+        for (grid, field) in self.trajectory.drain(..) {
+            let index = self.values.index(grid.put(field, player));
+            if self.visits == Visits::First && visited.contains(&index) {
+                continue;
+            }
+            visited.push(index);
+
+            let step_size = self
+                .hyperparameters
+                .step_size_after(self.values.visits[index]);
+            self.values[index] +=
+                step_size * (game_return - self.values[index]);
+        }
+        self.explorer.game_over();
+    }
+}
+
+impl Learner for MonteCarlo {
+    fn greedy(&self) -> Box<dyn Policy + '_> {
+        Box::new(Greedy {
+            values: &self.values,
+        })
+    }
+
+    fn values(&self) -> Option<&Values> {
+        Some(&self.values)
+    }
+}
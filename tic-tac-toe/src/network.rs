@@ -0,0 +1,178 @@
+//! Values of grids given by a small neural network, the way TD-Gammon learned
+//! backgammon. The network sees each field one-hot encoded, has one hidden
+//! layer of sigmoid units and a sigmoid output, so its values stay between 0
+//! and 1 like the probabilities of winning in the table.
+
+use super::semi_gradient::{Differentiable, SemiGradientLearner};
+use super::{Field, Grid, Player, SeededRng, ValueFunction};
+use rand::prelude::*;
+
+/// How many inputs describe a grid: whether each field is empty, has the
+/// player's mark or has the opponent's mark.
+pub const INPUTS: usize = 27;
+
+/// A multilayer perceptron with one hidden layer. All its weights are kept in
+/// one vector so that eligibility traces can follow them in the same order:
+/// for each hidden unit its bias and a weight for each input, then the bias of
+/// the output and a weight for each hidden unit.
+#[derive(Clone, Debug)]
+pub struct Network {
+    /// The player the values are of.
+    pub player: Player,
+    /// How many units there are in the hidden layer.
+    pub hidden: usize,
+    /// The weights of both layers, see the layout above.
+    pub weights: Vec<f32>,
+}
+
+/// Learns the weights of the network by TD(λ) like `LinearLearner` does, with
+/// the gradient of the value computed by backpropagation.
+pub type NetworkLearner = SemiGradientLearner<Network>;
+
+/// One-hot encodes the fields of the grid from the perspective of given
+/// player, three inputs per field.
+pub fn inputs(grid: Grid, player: Player) -> [f32; INPUTS] {
+    let mut inputs = [0.0; INPUTS];
+    for (i, field) in grid.fields().iter().enumerate() {
+        let hot = match field {
+            Field::Empty => 0,
+            field if *field == player => 1,
+            _ => 2,
+        };
+        inputs[i * 3 + hot] = 1.0;
+    }
+    inputs
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Network {
+    /// A network with given number of hidden units and small random weights,
+    /// which values every grid at about 0.5.
+    pub fn new(rng: &mut SeededRng, player: Player, hidden: usize) -> Self {
+        let bound = 1.0 / (INPUTS as f32).sqrt();
+        let weights = (0..Self::weight_count(hidden))
+            .map(|_| rng.gen_range(-bound, bound))
+            .collect();
+        Self {
+            player,
+            hidden,
+            weights,
+        }
+    }
+
+    /// How many weights a network with given number of hidden units has.
+    pub fn weight_count(hidden: usize) -> usize {
+        hidden * (INPUTS + 1) + hidden + 1
+    }
+
+    /// The activations of the hidden units and the output for given inputs.
+    fn forward(&self, inputs: &[f32; INPUTS]) -> (Vec<f32>, f32) {
+        let (hidden_weights, output_weights) =
+            self.weights.split_at(self.hidden * (INPUTS + 1));
+        let hidden: Vec<_> = hidden_weights
+            .chunks(INPUTS + 1)
+            .map(|unit| {
+                let sum: f32 =
+                    unit[1..].iter().zip(inputs).map(|(w, x)| w * x).sum();
+                sigmoid(unit[0] + sum)
+            })
+            .collect();
+        let sum: f32 = output_weights[1..]
+            .iter()
+            .zip(&hidden)
+            .map(|(w, h)| w * h)
+            .sum();
+        let output = sigmoid(output_weights[0] + sum);
+        (hidden, output)
+    }
+
+    /// The value of a grid where the game goes on.
+    pub fn estimate(&self, grid: Grid) -> f32 {
+        self.forward(&inputs(grid, self.player)).1
+    }
+}
+
+impl ValueFunction for Network {
+    /// Finished games are valued exactly, like in the table: 1.0 for a win
+    /// and 0.0 for a draw or a loss.
+    fn value(&self, grid: Grid) -> f32 {
+        if grid.outcome().is_over() {
+            (grid.outcome().winner() == Some(self.player)) as u8 as f32
+        } else {
+            self.estimate(grid)
+        }
+    }
+}
+
+impl Differentiable for Network {
+    /// The value of a grid where the game goes on and its gradient with
+    /// respect to each weight, found by backpropagation.
+    fn gradient(&self, grid: Grid) -> (f32, Vec<f32>) {
+        let inputs = inputs(grid, self.player);
+        let (hidden, output) = self.forward(&inputs);
+        let output_delta = output * (1.0 - output);
+        let output_weights = &self.weights[self.hidden * (INPUTS + 1)..];
+
+        let mut gradient = Vec::with_capacity(self.weights.len());
+        for (h, w) in hidden.iter().zip(&output_weights[1..]) {
+            let delta = output_delta * w * h * (1.0 - h);
+            gradient.push(delta);
+            gradient.extend(inputs.iter().map(|x| delta * x));
+        }
+        gradient.push(output_delta);
+        gradient.extend(hidden.iter().map(|h| output_delta * h));
+        (output, gradient)
+    }
+
+    fn weights_mut(&mut self) -> &mut [f32] {
+        &mut self.weights
+    }
+
+    fn network(&self) -> Option<&Network> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> Network {
+        Network::new(&mut SeededRng::seed_from_u64(7), Player::O, 3)
+    }
+
+    fn grid() -> Grid {
+        Grid::new()
+            .put(4, Player::X)
+            .put(0, Player::O)
+            .put(8, Player::X)
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences() {
+        let network = network();
+        let (value, gradient) = network.gradient(grid());
+        assert_eq!(value, network.estimate(grid()));
+        assert_eq!(gradient.len(), Network::weight_count(3));
+
+        let epsilon = 1e-2;
+        for (i, g) in gradient.iter().enumerate() {
+            let mut plus = network.clone();
+            plus.weights[i] += epsilon;
+            let mut minus = network.clone();
+            minus.weights[i] -= epsilon;
+            let difference = (plus.estimate(grid()) - minus.estimate(grid()))
+                / (2.0 * epsilon);
+            assert!(
+                (g - difference).abs() < 1e-3,
+                "weight {}: gradient {} but finite difference {}",
+                i,
+                g,
+                difference
+            );
+        }
+    }
+}
@@ -1,53 +1,93 @@
-use super::{Field, Grid};
+//! Policies pick the field to mark in each grid. Any policy can play any
+//! other in `play_game`.
+
+use super::{Grid, Player, SeededRng};
 use rand::prelude::*;
-use std::io;
-use std::io::prelude::*;
-
-/// Picks a random action from the set of possible moves. Panics if the state
-/// has no action for the policy to pick.
-pub(super) fn random(
-    rng: &mut ThreadRng,
-    _: Grid,
-    actions: &mut Vec<usize>,
-) -> usize {
-    debug_assert_ne!(0, actions.len());
-    let i = rng.gen_range(0, actions.len());
-    actions.swap_remove(i)
+
+/// Anything that can sit at the board and make moves. A policy is told which
+/// player it plays for in each call, therefore the same policy can fill either
+/// seat.
+pub trait Policy {
+    /// Picks one of the given actions, i.e. indices of empty fields in the
+    /// grid. Panics if there is no action to pick.
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize;
+
+    /// Learning hook which is called once the game is over with the final
+    /// grid. Policies which don't learn don't have to implement it.
+    fn game_over(&mut self, _grid: Grid, _player: Player) {}
 }
 
-/// Asks for human input.
-pub(super) fn human(
-    _rng: &mut ThreadRng,
-    grid: Grid,
-    actions: &mut Vec<usize>,
-) -> usize {
-    let field = |i: usize| match grid.fields[i] {
-        Field::Empty => {
-            actions.iter().position(|a| *a == i).unwrap().to_string()
+/// Picks a random action from the set of possible moves.
+pub struct Random;
+
+/// Lets a policy which learned to play one side play either side. When asked
+/// to play the other side, the inner policy sees the grid with Xs and Os
+/// swapped, so its own marks are always those of its side.
+pub struct AnySide<'a> {
+    /// The policy which plays as `side`.
+    pub policy: &'a mut dyn Policy,
+    /// The side the inner policy learned to play.
+    pub side: Player,
+}
+
+impl Policy for Random {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        _: Grid,
+        _: Player,
+        actions: &[usize],
+    ) -> usize {
+        debug_assert_ne!(0, actions.len());
+        *actions
+            .choose(rng)
+            .expect("There must be an action to pick")
+    }
+}
+
+impl Policy for AnySide<'_> {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        if player == self.side {
+            self.policy.pick(rng, grid, player, actions)
+        } else {
+            self.policy
+                .pick(rng, grid.swap_players(), self.side, actions)
+        }
+    }
+
+    fn game_over(&mut self, grid: Grid, player: Player) {
+        if player == self.side {
+            self.policy.game_over(grid, player)
+        } else {
+            self.policy.game_over(grid.swap_players(), self.side)
         }
-        field => field.to_string(),
-    };
-
-    println!(" {} | {} | {} ", field(0), field(1), field(2));
-    println!("---+---+---");
-    println!(" {} | {} | {} ", field(3), field(4), field(5));
-    println!("---+---+---");
-    println!(" {} | {} | {} ", field(6), field(7), field(8));
-
-    let stdin = io::stdin();
-    let handle = stdin
-        .lock()
-        .lines()
-        .next()
-        .unwrap()
-        .expect("Expected a string stdin input");
-    let i = handle
-        .parse::<usize>()
-        .expect("Cannot parse given string to a number");
-    assert!(
-        i < actions.len(),
-        "You must provide a number less than {}.",
-        actions.len()
-    );
-    actions.swap_remove(i)
+    }
+}
+
+impl<P: Policy + ?Sized> Policy for Box<P> {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        (**self).pick(rng, grid, player, actions)
+    }
+
+    fn game_over(&mut self, grid: Grid, player: Player) {
+        (**self).game_over(grid, player)
+    }
 }
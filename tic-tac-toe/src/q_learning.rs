@@ -0,0 +1,185 @@
+//! Rather than valuing grids, the learners in this module value each action in
+//! each grid. The value of an action is how likely the learner is to win if it
+//! marks the field and then keeps playing the way it does.
+
+use super::exploration::Explorer;
+use super::learner::{Hyperparameters, Learner};
+use super::num_ext::*;
+use super::policies::Policy;
+use super::symmetry::Transform;
+use super::{GameOutcome, Grid, Indexing, Player, SeededRng};
+
+/// The value of each of the 9 fields in each grid. Values of fields which
+/// are not empty are never used.
+#[derive(Clone, Debug)]
+pub struct ActionValues {
+    /// How the grids are mapped to their positions in the estimates.
+    pub indexing: Indexing,
+    /// The estimated value of marking each field, by position of the grid.
+    pub estimates: Vec<[f32; 9]>,
+}
+
+/// What the value of the previous action is moved towards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Update {
+    /// The value of the best action in the next grid, regardless of which
+    /// action is then taken.
+    QLearning,
+    /// The value of the action which is taken in the next grid, even if it is
+    /// an exploratory one.
+    Sarsa,
+}
+
+/// Learns action values by temporal difference as it plays. The reward is 1.0
+/// for a win and 0.0 for a draw or a loss, the same as the values of final
+/// grids in `initial_values`.
+pub struct QLearner {
+    /// The values of the actions in each grid.
+    pub action_values: ActionValues,
+    update: Update,
+    hyperparameters: Hyperparameters,
+    explorer: Explorer,
+    // The index of the grid in which the learner made its previous move and
+    // the field it marked, as stored in the action values.
+    last_action: Option<(usize, usize)>,
+}
+
+/// Always picks the action with the highest value and never learns.
+pub struct GreedyQ<'a> {
+    /// The action values the policy goes by.
+    pub action_values: &'a ActionValues,
+}
+
+impl ActionValues {
+    /// All actions start with the value of 0.5, i.e. the outcome is uncertain.
+    pub fn new(indexing: Indexing) -> Self {
+        Self {
+            indexing,
+            estimates: vec![[0.5; 9]; indexing.len()],
+        }
+    }
+
+    /// Index of the grid and the transform which maps fields of the grid to
+    /// fields as they are stored. Symmetrical grids share their actions, so
+    /// with canonical indexing the fields are those of the canonical grid.
+    fn locate(&self, grid: Grid) -> (usize, Transform) {
+        match self.indexing {
+            Indexing::Canonical => {
+                let (canonical, transform) = grid.canonical();
+                (canonical.to_base_10(), transform)
+            }
+            indexing => (indexing.index(grid), Transform::Identity),
+        }
+    }
+
+    /// Finds the action with the highest value. Returns the field to mark in
+    /// the grid and the value of the action.
+    pub fn best_action(&self, grid: Grid, actions: &[usize]) -> (usize, f32) {
+        let (index, transform) = self.locate(grid);
+        let mut actions: Vec<_> =
+            actions.iter().map(|a| transform.map_field(*a)).collect();
+        actions.sort_unstable();
+
+        let (field, value) = actions
+            .into_iter()
+            .map(|field| (field, self.estimates[index][field]))
+            .max_by(|(_, value), (_, another_value)| {
+                (*value).partial_ord(*another_value)
+            })
+            .expect("There must be at least one action to take");
+
+        (transform.inverse().map_field(field), value)
+    }
+}
+
+impl QLearner {
+    /// Creates a learner with initial action values, which updates them by
+    /// Q-learning or SARSA.
+    pub fn new(
+        update: Update,
+        indexing: Indexing,
+        hyperparameters: Hyperparameters,
+    ) -> Self {
+        Self {
+            action_values: ActionValues::new(indexing),
+            update,
+            hyperparameters,
+            explorer: Explorer::new(
+                hyperparameters.exploration,
+                hyperparameters.exploration_probability,
+            ),
+            last_action: None,
+        }
+    }
+
+    /// Moves the value of the previous action towards the target.
+    fn learn(&mut self, target: f32) {
+        if let Some((index, field)) = self.last_action {
+            let value = &mut self.action_values.estimates[index][field];
+            *value += self.hyperparameters.step_size * (target - *value);
+        }
+    }
+}
+
+impl Policy for QLearner {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        _: Player,
+        actions: &[usize],
+    ) -> usize {
+        let (best_field, best_value) =
+            self.action_values.best_action(grid, actions);
+
+        let (index, transform) = self.action_values.locate(grid);
+        let estimates = &self.action_values.estimates[index];
+        let explored = self.explorer.explore(rng, actions, |field| {
+            let stored_field = transform.map_field(field);
+            (estimates[stored_field], index * 9 + stored_field)
+        });
+        let field_to_mark = explored.unwrap_or(best_field);
+
+        let stored_field = transform.map_field(field_to_mark);
+        self.explorer.visit(index * 9 + stored_field);
+        let target = match self.update {
+            Update::QLearning => best_value,
+            Update::Sarsa => self.action_values.estimates[index][stored_field],
+        };
+        self.learn(target);
+
+        self.last_action = Some((index, stored_field));
+        field_to_mark
+    }
+
+    fn game_over(&mut self, grid: Grid, player: Player) {
+        let reward = match grid.outcome() {
+            GameOutcome::Ongoing => panic!("The game must be over"),
+            outcome if outcome.winner() == Some(player) => 1.0,
+            _ => 0.0,
+        };
+        self.learn(reward);
+        self.last_action = None;
+        self.explorer.game_over();
+    }
+}
+
+impl Learner for QLearner {
+    fn greedy(&self) -> Box<dyn Policy + '_> {
+        Box::new(GreedyQ {
+            action_values: &self.action_values,
+        })
+    }
+}
+
+impl Policy for GreedyQ<'_> {
+    fn pick(
+        &mut self,
+        _rng: &mut SeededRng,
+        grid: Grid,
+        _: Player,
+        actions: &[usize],
+    ) -> usize {
+        self.action_values.best_action(grid, actions).0
+    }
+}
@@ -0,0 +1,85 @@
+//! Most of the 3^9 grids can never come up in a game. For example both players
+//! can't have 3 in a row, and the player who moved first never has fewer
+//! marks than the other one. Only 5478 grids can be reached by legal play when
+//! X moves first, and 8533 when either player can move first. This module
+//! enumerates them and gives each a dense slot.
+
+use super::{Grid, Player};
+use std::sync::OnceLock;
+
+/// Marks grids which have no slot.
+const UNREACHABLE: u16 = u16::MAX;
+
+/// Perfect index of the reachable grids.
+pub struct ReachableGrids {
+    /// Reachable grids. The position of a grid is its slot. First come the
+    /// grids reachable when X moves first ordered by their ordinal, then the
+    /// grids which are only reachable when O moves first, again ordered by
+    /// their ordinal. Values stored before O could move first therefore keep
+    /// their slots.
+    pub grids: Vec<Grid>,
+    /// How many grids are reachable when X moves first.
+    pub x_first: usize,
+    // For each ordinal of a grid, its slot or `UNREACHABLE`.
+    slots: Vec<u16>,
+}
+
+impl ReachableGrids {
+    /// The index is built on first use and then shared.
+    pub fn get() -> &'static Self {
+        static REACHABLE: OnceLock<ReachableGrids> = OnceLock::new();
+        REACHABLE.get_or_init(Self::enumerate)
+    }
+
+    /// Dense slot of the grid, or `None` if the grid can't come up in a game.
+    pub fn slot(&self, grid: Grid) -> Option<usize> {
+        match self.slots[grid.to_base_10()] {
+            UNREACHABLE => None,
+            slot => Some(slot as usize),
+        }
+    }
+
+    /// Plays out every possible game from the empty grid.
+    fn enumerate() -> Self {
+        let mut grids = Vec::new();
+        let mut slots = vec![UNREACHABLE; 3usize.pow(9)];
+        let mut x_first = 0;
+        for first in &[Player::X, Player::O] {
+            let mut is_reachable = vec![false; slots.len()];
+            let mut stack = vec![(Grid::new(), *first)];
+            while let Some((grid, player)) = stack.pop() {
+                let ordinal = grid.to_base_10();
+                if is_reachable[ordinal] {
+                    continue;
+                }
+                is_reachable[ordinal] = true;
+
+                if !grid.outcome().is_over() {
+                    for field in grid.empty_fields() {
+                        stack
+                            .push((grid.put(field, player), player.opponent()));
+                    }
+                }
+            }
+
+            for (ordinal, _) in
+                is_reachable.iter().enumerate().filter(|(_, is)| **is)
+            {
+                if slots[ordinal] == UNREACHABLE {
+                    slots[ordinal] = grids.len() as u16;
+                    grids.push(Grid::from_base_10(ordinal));
+                }
+            }
+
+            if *first == Player::X {
+                x_first = grids.len();
+            }
+        }
+
+        Self {
+            grids,
+            x_first,
+            slots,
+        }
+    }
+}
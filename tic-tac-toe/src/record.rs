@@ -0,0 +1,428 @@
+//! Records of played games. A record file holds any number of games, each as
+//! a block of `key value` lines, separated by empty lines:
+//!
+//! ```text
+//! event train
+//! game 1000
+//! x agent
+//! o random
+//! seed 42
+//! first x
+//! moves b2 a1 c3 a3 a2 c2 c1 b1 b3
+//! result draw
+//! ```
+//!
+//! The game is numbered within the event it was played in, and together with
+//! the seed of the run it identifies the game. The moves are named by their
+//! column and row. The result is `x`, `o` or `draw`. If the moves don't finish
+//! the game, the loser resigned.
+
+use super::policies::Policy;
+use super::{
+    field_name, parse_field, play_game, play_recorded_game, GameOutcome, Grid,
+    Player, SeededRng, ValueFunction,
+};
+use std::fs::{File, OpenOptions};
+use std::io::{self, prelude::*, BufWriter, IsTerminal};
+use std::path::Path;
+
+/// One game as it was played.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    /// What the game was played for, such as training or a human game.
+    pub event: String,
+    /// The number of the game within its event.
+    pub game: u64,
+    /// The name of the player who played X.
+    pub x: String,
+    /// The name of the player who played O.
+    pub o: String,
+    /// The seed of the run the game was played in.
+    pub seed: u64,
+    /// The player who moved first.
+    pub first: Player,
+    /// The fields marked in order.
+    pub moves: Vec<usize>,
+    /// How the game ended. A game whose moves don't finish it was resigned.
+    pub result: GameOutcome,
+}
+
+/// Appends the records of played games to a file.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    seed: u64,
+    /// Every how many training games one is recorded, if at all.
+    every: Option<u64>,
+    /// The event the following games are played for and the names of the two
+    /// players in it.
+    event: String,
+    names: (String, String),
+}
+
+impl GameRecord {
+    /// The grids after each move, starting with the empty grid. Fails if a
+    /// move is illegal.
+    pub fn grids(&self) -> Result<Vec<Grid>, String> {
+        let mut grids = vec![Grid::new()];
+        let mut player = self.first;
+        for (i, field) in self.moves.iter().enumerate() {
+            let grid = grids[i];
+            if grid.outcome().is_over() {
+                return Err(format!(
+                    "Game {} goes on after it is over",
+                    self.game
+                ));
+            }
+            if !grid.empty_fields().contains(field) {
+                return Err(format!(
+                    "Move {} of game {} marks a taken field",
+                    i + 1,
+                    self.game
+                ));
+            }
+            grids.push(grid.put(*field, player));
+            player = player.opponent();
+        }
+        Ok(grids)
+    }
+
+    /// The name of the player.
+    pub fn name(&self, player: Player) -> &str {
+        match player {
+            Player::X => &self.x,
+            Player::O => &self.o,
+        }
+    }
+}
+
+impl Recorder {
+    /// Creates the file, or appends to it if it exists.
+    pub fn create(
+        path: impl AsRef<Path>,
+        seed: u64,
+        every: Option<u64>,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            seed,
+            every,
+            event: String::new(),
+            names: Default::default(),
+        })
+    }
+
+    /// Names the event the following games are played for and the two players
+    /// who play them.
+    pub fn start(&mut self, event: &str, first: &str, second: &str) {
+        self.event = event.to_string();
+        self.names = (first.to_string(), second.to_string());
+    }
+
+    /// Whether the training game with given number should be recorded. The
+    /// games are numbered from 1. Games which aren't training games are
+    /// always recorded.
+    pub fn samples(&self, game: u64) -> bool {
+        let training = matches!(self.event.as_str(), "train" | "self-play");
+        !training || self.every.is_some_and(|every| game % every == 0)
+    }
+
+    /// Writes the record of a game of the current event, in which the first
+    /// of the players played given side.
+    pub fn record(
+        &mut self,
+        game: u64,
+        side: Player,
+        first: Player,
+        moves: Vec<usize>,
+        result: GameOutcome,
+    ) -> io::Result<()> {
+        let (a, b) = &self.names;
+        let (x, o) = match side {
+            Player::X => (a, b),
+            Player::O => (b, a),
+        };
+        let record = GameRecord {
+            event: self.event.clone(),
+            game,
+            x: x.clone(),
+            o: o.clone(),
+            seed: self.seed,
+            first,
+            moves,
+            result,
+        };
+        write(&mut self.writer, &record)?;
+        self.writer.flush()
+    }
+}
+
+/// Plays one game like `play_game`. If there is a recorder which samples the
+/// game, the game is recorded with the first player on given side.
+pub fn play_sampled(
+    rng: &mut SeededRng,
+    (x, o): (&mut dyn Policy, &mut dyn Policy),
+    side: Player,
+    first: Player,
+    game: u64,
+    recorder: Option<&mut Recorder>,
+) -> io::Result<Grid> {
+    match recorder.filter(|recorder| recorder.samples(game)) {
+        Some(recorder) => {
+            let (grid, moves) = play_recorded_game(rng, x, o, first);
+            recorder.record(game, side, first, moves, grid.outcome())?;
+            Ok(grid)
+        }
+        None => Ok(play_game(rng, x, o, first)),
+    }
+}
+
+fn write(writer: &mut impl Write, record: &GameRecord) -> io::Result<()> {
+    let moves: Vec<_> = record
+        .moves
+        .iter()
+        .map(|field| field_name(*field))
+        .collect();
+    let result = match record.result {
+        GameOutcome::XWins => "x",
+        GameOutcome::OWins => "o",
+        GameOutcome::Draw | GameOutcome::Ongoing => "draw",
+    };
+    writeln!(writer, "event {}", record.event)?;
+    writeln!(writer, "game {}", record.game)?;
+    writeln!(writer, "x {}", record.x)?;
+    writeln!(writer, "o {}", record.o)?;
+    writeln!(writer, "seed {}", record.seed)?;
+    writeln!(
+        writer,
+        "first {}",
+        format!("{:?}", record.first).to_lowercase()
+    )?;
+    writeln!(writer, "moves {}", moves.join(" "))?;
+    writeln!(writer, "result {}", result)?;
+    writeln!(writer)
+}
+
+/// Reads all the records in the file.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<GameRecord>, String> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    parse(&text)
+}
+
+fn parse(text: &str) -> Result<Vec<GameRecord>, String> {
+    let mut records = Vec::new();
+    let mut lines = text.lines().map(str::trim).peekable();
+    loop {
+        while lines.peek() == Some(&"") {
+            lines.next();
+        }
+        if lines.peek().is_none() {
+            return Ok(records);
+        }
+
+        let mut value = |key: &str| {
+            let line = lines
+                .next()
+                .ok_or_else(|| format!("Record ends before its {}", key))?;
+            let (found, value) = line.split_once(' ').unwrap_or((line, ""));
+            if found == key {
+                Ok(value.to_string())
+            } else {
+                Err(format!("Expected {} in the record, not '{}'", key, line))
+            }
+        };
+        let number = |value: String| {
+            value
+                .parse()
+                .map_err(|_| format!("Expected a number, not '{}'", value))
+        };
+        let event = value("event")?;
+        let game = number(value("game")?)?;
+        let x = value("x")?;
+        let o = value("o")?;
+        let seed = number(value("seed")?)?;
+        let first = value("first")?.parse()?;
+        let moves = value("moves")?
+            .split_whitespace()
+            .map(|field| {
+                parse_field(field)
+                    .ok_or_else(|| format!("Unknown field '{}'", field))
+            })
+            .collect::<Result<_, _>>()?;
+        let result = match value("result")?.as_str() {
+            "x" => GameOutcome::XWins,
+            "o" => GameOutcome::OWins,
+            "draw" => GameOutcome::Draw,
+            result => return Err(format!("Unknown result '{}'", result)),
+        };
+
+        records.push(GameRecord {
+            event,
+            game,
+            x,
+            o,
+            seed,
+            first,
+            moves,
+            result,
+        });
+    }
+}
+
+/// Shows the game move by move, with the value the agent gives each grid for
+/// the player who just moved into it. When the input is a terminal, it waits
+/// for enter before each move. Returns false if the viewer wants to stop.
+pub fn replay(
+    record: &GameRecord,
+    values: Option<(&dyn ValueFunction, Player)>,
+    input: &mut impl BufRead,
+) -> bool {
+    println!(
+        "\nGame {} of {}, seed {}: X {}, O {}, {:?} moves first.",
+        record.game,
+        record.event,
+        record.seed,
+        record.x,
+        record.o,
+        record.first
+    );
+    let grids = match record.grids() {
+        Ok(grids) => grids,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+
+    let interactive = io::stdin().is_terminal();
+    let mut player = record.first;
+    for (i, (field, grid)) in record.moves.iter().zip(&grids[1..]).enumerate() {
+        if interactive {
+            print!("Press enter for the next move, or type quit: ");
+            io::stdout().flush().ok();
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) | Err(_) => return false,
+                Ok(_) if line.trim() == "quit" => return false,
+                Ok(_) => (),
+            }
+        }
+
+        match values {
+            Some((values, values_player)) => println!(
+                "\nMove {}: {:?} marks {}, the agent values it {:.3} for {:?}.",
+                i + 1,
+                player,
+                field_name(*field),
+                values.afterstate_value(*grid, player, values_player),
+                player
+            ),
+            None => {
+                println!(
+                    "\nMove {}: {:?} marks {}.",
+                    i + 1,
+                    player,
+                    field_name(*field)
+                )
+            }
+        }
+        grid.print();
+        player = player.opponent();
+    }
+
+    let last = grids[grids.len() - 1];
+    println!();
+    match (record.result.winner(), last.outcome().is_over()) {
+        (Some(winner), true) => {
+            println!("{:?} ({}) won.", winner, record.name(winner))
+        }
+        (Some(winner), false) => {
+            let loser = winner.opponent();
+            println!("{:?} ({}) resigned.", loser, record.name(loser))
+        }
+        (None, _) => println!("It's a draw."),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<GameRecord> {
+        vec![
+            GameRecord {
+                event: "train".to_string(),
+                game: 1000,
+                x: "agent".to_string(),
+                o: "random".to_string(),
+                seed: 42,
+                first: Player::X,
+                moves: vec![4, 0, 8, 2, 1, 7, 6, 3, 5],
+                result: GameOutcome::Draw,
+            },
+            GameRecord {
+                event: "human".to_string(),
+                game: 3,
+                x: "human player".to_string(),
+                o: "agent".to_string(),
+                seed: u64::MAX,
+                first: Player::O,
+                moves: vec![4, 0],
+                result: GameOutcome::OWins,
+            },
+        ]
+    }
+
+    #[test]
+    fn write_then_parse_round_trips() {
+        let records = records();
+        let mut bytes = Vec::new();
+        for record in &records {
+            write(&mut bytes, record).unwrap();
+        }
+        let text = String::from_utf8(bytes).unwrap();
+        assert_eq!(parse(&text).unwrap(), records);
+        assert_eq!(parse(&format!("\n\n{}\n\n", text)).unwrap(), records);
+    }
+
+    #[test]
+    fn writes_the_documented_format() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &records()[0]).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "event train\ngame 1000\nx agent\no random\nseed 42\nfirst x\n\
+             moves b2 a1 c3 c1 b1 b3 a3 a2 c2\nresult draw\n\n"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed_records() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &records()[0]).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        for (from, to) in [
+            ("game 1000", "game many"),
+            ("first x", "first z"),
+            ("moves b2", "moves d4"),
+            ("result draw", "result tie"),
+            ("seed 42\n", ""),
+        ] {
+            assert!(parse(&text.replace(from, to)).is_err(), "{}", to);
+        }
+        assert!(parse(&text[..text.find("result").unwrap()]).is_err());
+    }
+
+    #[test]
+    fn grids_reject_illegal_moves() {
+        let mut record = records()[1].clone();
+        assert_eq!(record.grids().unwrap().len(), 3);
+        record.moves = vec![4, 4];
+        assert!(record.grids().is_err());
+        record.moves = vec![0, 3, 1, 4, 2, 5];
+        assert!(record.grids().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Learning of values which are computed from weights rather than kept in a
+//! table. Whatever computes the value, the weights are learned the same way,
+//! by semi-gradient temporal difference, as long as the gradient of the value
+//! with respect to each weight is known.
+
+use super::exploration::Explorer;
+use super::learner::{Greedy, Hyperparameters, Learner, Traces};
+use super::network::Network;
+use super::policies::Policy;
+use super::{Grid, Player, SeededRng, ValueFunction};
+
+/// Values of grids computed from weights which can be learned by gradient.
+pub trait Differentiable: ValueFunction {
+    /// The estimated value of a grid where the game goes on and its gradient
+    /// with respect to each weight, in the order of `weights_mut`.
+    fn gradient(&self, grid: Grid) -> (f32, Vec<f32>);
+
+    /// The weights the value is computed from.
+    fn weights_mut(&mut self) -> &mut [f32];
+
+    /// The values as a network, if they are one.
+    fn network(&self) -> Option<&Network> {
+        None
+    }
+}
+
+/// Learns the weights by semi-gradient TD(λ). Each temporal difference moves
+/// every weight in proportion to its eligibility, which adds up the gradients
+/// of the value in the states of the game and decays by λ with each move.
+/// Replacing traces reset the eligibility of each weight whose gradient isn't
+/// zero to the gradient instead of adding it.
+///
+/// There is no count of visits to a grid, so the step size schedule is
+/// ignored and the step size stays constant.
+pub struct SemiGradientLearner<V> {
+    /// The values learned so far.
+    pub values: V,
+    hyperparameters: Hyperparameters,
+    explorer: Explorer,
+    // The grid which resulted from the learner's previous move in the current
+    // game. This is the state whose value gets updated next.
+    last_afterstate: Option<Grid>,
+    // The eligibility of each weight.
+    eligibility: Vec<f32>,
+}
+
+impl<V: Differentiable> SemiGradientLearner<V> {
+    /// Creates a learner which continues learning given values.
+    pub fn new(mut values: V, hyperparameters: Hyperparameters) -> Self {
+        let eligibility = vec![0.0; values.weights_mut().len()];
+        Self {
+            values,
+            hyperparameters,
+            explorer: Explorer::new(
+                hyperparameters.exploration,
+                hyperparameters.exploration_probability,
+            ),
+            last_afterstate: None,
+            eligibility,
+        }
+    }
+
+    /// Updates the weights by the temporal difference between the previous
+    /// state and the next one.
+    fn backup(&mut self, state: Grid, next_state_value: f32) {
+        let Hyperparameters {
+            step_size,
+            lambda,
+            traces,
+            ..
+        } = self.hyperparameters;
+        let (value, gradient) = self.values.gradient(state);
+        let td_error = next_state_value - value;
+
+        for ((weight, e), g) in self
+            .values
+            .weights_mut()
+            .iter_mut()
+            .zip(&mut self.eligibility)
+            .zip(&gradient)
+        {
+            *e = match traces {
+                Traces::Replacing if *g != 0.0 => *g,
+                _ => *e + g,
+            };
+            *weight += step_size * td_error * *e;
+            *e *= lambda;
+        }
+    }
+
+    fn forget(&mut self) {
+        self.eligibility.iter_mut().for_each(|e| *e = 0.0);
+    }
+}
+
+impl<V: Differentiable> Policy for SemiGradientLearner<V> {
+    fn pick(
+        &mut self,
+        rng: &mut SeededRng,
+        grid: Grid,
+        player: Player,
+        actions: &[usize],
+    ) -> usize {
+        // Like the table learner, exploratory moves don't update the weights
+        // and the eligibility of the states before them is forgotten. The
+        // explorer identifies the afterstates by their ternary index.
+        let values = &self.values;
+        let explored = self.explorer.explore(rng, actions, |field| {
+            let afterstate = grid.put(field, player);
+            (values.value(afterstate), afterstate.to_base_10())
+        });
+        let field_to_mark = match explored {
+            Some(field) => {
+                self.forget();
+                field
+            }
+            None => self.values.best_field(grid, player, actions),
+        };
+        let afterstate = grid.put(field_to_mark, player);
+        self.explorer.visit(afterstate.to_base_10());
+
+        if explored.is_none() {
+            if let Some(state) = self.last_afterstate {
+                self.backup(state, self.values.value(afterstate));
+            }
+        }
+
+        self.last_afterstate = Some(afterstate);
+        field_to_mark
+    }
+
+    fn game_over(&mut self, grid: Grid, _: Player) {
+        // If the opponent made the last move, the state we left them with is
+        // updated towards the final state, whose value is exact. If the
+        // learner made the last move, its afterstate is the final state and
+        // there is nothing to learn about it.
+        if let Some(state) = self.last_afterstate.take() {
+            if state != grid {
+                self.backup(state, self.values.value(grid));
+            }
+        }
+        self.forget();
+        self.explorer.game_over();
+    }
+}
+
+impl<V: Differentiable> Learner for SemiGradientLearner<V> {
+    fn greedy(&self) -> Box<dyn Policy + '_> {
+        Box::new(Greedy {
+            values: &self.values,
+        })
+    }
+
+    fn value_function(&self) -> Option<&dyn ValueFunction> {
+        Some(&self.values)
+    }
+
+    fn network(&self) -> Option<&Network> {
+        self.values.network()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn hyperparameters(lambda: f32) -> Hyperparameters {
+        Hyperparameters {
+            step_size: 0.1,
+            lambda,
+            ..Hyperparameters::default()
+        }
+    }
+
+    #[test]
+    fn backup_moves_value_towards_target() {
+        let grid = Grid::new().put(4, Player::X).put(0, Player::O);
+        let network =
+            Network::new(&mut SeededRng::seed_from_u64(7), Player::X, 3);
+        for target in [0.0, 1.0] {
+            let mut learner =
+                SemiGradientLearner::new(network.clone(), hyperparameters(0.5));
+            let before = learner.values.value(grid);
+            learner.backup(grid, target);
+            let after = learner.values.value(grid);
+            assert!(
+                (target - after).abs() < (target - before).abs(),
+                "{} moved to {} rather than towards {}",
+                before,
+                after,
+                target
+            );
+        }
+    }
+}
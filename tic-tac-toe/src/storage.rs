@@ -0,0 +1,933 @@
+//! Saving and loading of trained values. There are two formats, a compact
+//! binary one and a text one which is easy to inspect or diff. Both start with
+//! a header which says how the values were trained.
+//!
+//! The binary format is little endian:
+//!
+//! ```text
+//! magic         4 bytes   "TTTV"
+//! version       u16
+//! player        u8        'X' or 'O'
+//! indexing      u8        't' ternary, 'c' canonical or 'r' reachable
+//! step size     f32
+//! exploration   f32
+//! games         u64
+//! value count   u32
+//! values        f32 * value count
+//! visits        u32 * value count
+//! ```
+//!
+//! The text format has the same header, one `key value` pair per line,
+//! followed by the values, one per line, and then by `visits <count>` and the
+//! visits, one per line.
+//!
+//! Version 1 of the formats had no indexing and its values are always ternary.
+//! Up to version 2, only the grids reachable when X moves first had a slot in
+//! the reachable indexing. The grids which are only reachable when O moves
+//! first are given their initial values on load. Up to version 3, the visits
+//! were not saved and are loaded as zeros.
+//!
+//! Networks are saved the same way, with their own magic and version. In place
+//! of the indexing and the values, the binary format has:
+//!
+//! ```text
+//! inputs        u32
+//! hidden units  u32
+//! weights       f32 * weight count
+//! ```
+//!
+//! and the text format has `inputs <count>`, `hidden <count>` and
+//! `weights <count>` lines, followed by the weights, one per line.
+
+use super::network::{Network, INPUTS};
+use super::reachable::ReachableGrids;
+use super::{initial_values, Indexing, Player, ValueFunction, Values};
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+
+/// Identifies the binary format.
+const MAGIC: &[u8; 4] = b"TTTV";
+
+/// The first line of the text format.
+const TEXT_MAGIC: &str = "tic-tac-toe values";
+
+/// Bumped whenever either of the formats changes.
+const VERSION: u16 = 4;
+
+/// Identifies the binary format of networks.
+const NETWORK_MAGIC: &[u8; 4] = b"TTTN";
+
+/// The first line of the text format of networks.
+const NETWORK_TEXT_MAGIC: &str = "tic-tac-toe network";
+
+/// Bumped whenever either of the formats of networks changes.
+const NETWORK_VERSION: u16 = 1;
+
+/// The most hidden units a loaded network may have. Far more than any network
+/// worth training for tic-tac-toe, but it keeps a corrupt file from asking for
+/// gigabytes of weights.
+const MAX_HIDDEN: usize = 1 << 16;
+
+/// Describes how a set of values was trained.
+#[derive(Clone, Copy, Debug)]
+pub struct Header {
+    /// The player whose values these are.
+    pub player: Player,
+    /// The step size the values were trained with.
+    pub step_size: f32,
+    /// The exploration probability the values were trained with.
+    pub exploration_probability: f32,
+    /// How many games the values were trained on.
+    pub training_games: u64,
+}
+
+/// Saves the values into a file. If the file has a `.txt` extension, the text
+/// format is used, otherwise the binary one.
+pub fn save(
+    path: impl AsRef<Path>,
+    header: &Header,
+    values: &Values,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path.extension().is_some_and(|ext| ext == "txt") {
+        write_text(&mut writer, header, values)?;
+    } else {
+        write_binary(&mut writer, header, values)?;
+    }
+    writer.flush()
+}
+
+/// Loads values from a file saved by `save`. The format is recognized by the
+/// first bytes of the file.
+pub fn load(path: impl AsRef<Path>) -> io::Result<(Header, Values)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(MAGIC) {
+        read_binary(&mut reader)
+    } else {
+        read_text(&mut reader)
+    }
+}
+
+/// Saves the network into a file, in the text format if the file has a
+/// `.txt` extension, like `save` does.
+pub fn save_network(
+    path: impl AsRef<Path>,
+    header: &Header,
+    network: &Network,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path.extension().is_some_and(|ext| ext == "txt") {
+        write_network_text(&mut writer, header, network)?;
+    } else {
+        write_network_binary(&mut writer, header, network)?;
+    }
+    writer.flush()
+}
+
+/// Loads a network from a file saved by `save_network`.
+pub fn load_network(path: impl AsRef<Path>) -> io::Result<(Header, Network)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(NETWORK_MAGIC) {
+        read_network_binary(&mut reader)
+    } else {
+        read_network_text(&mut reader)
+    }
+}
+
+/// Whether the file was saved by `save_network` rather than by `save`.
+pub fn is_network(path: impl AsRef<Path>) -> io::Result<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let start = reader.fill_buf()?;
+    Ok(start.starts_with(NETWORK_MAGIC)
+        || start.starts_with(NETWORK_TEXT_MAGIC.as_bytes()))
+}
+
+/// Loads either values or a network, whichever the file holds, for an agent
+/// which only plays by them.
+pub fn load_value_function(
+    path: impl AsRef<Path>,
+) -> io::Result<(Header, Box<dyn ValueFunction>)> {
+    let path = path.as_ref();
+    if is_network(path)? {
+        let (header, network) = load_network(path)?;
+        Ok((header, Box::new(network)))
+    } else {
+        let (header, values) = load(path)?;
+        Ok((header, Box::new(values)))
+    }
+}
+
+fn write_binary(
+    w: &mut impl Write,
+    header: &Header,
+    values: &Values,
+) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&[player_to_byte(header.player)])?;
+    w.write_all(&[indexing_to_byte(values.indexing)])?;
+    w.write_all(&header.step_size.to_le_bytes())?;
+    w.write_all(&header.exploration_probability.to_le_bytes())?;
+    w.write_all(&header.training_games.to_le_bytes())?;
+    w.write_all(&(values.estimates.len() as u32).to_le_bytes())?;
+    for value in &values.estimates {
+        w.write_all(&value.to_le_bytes())?;
+    }
+    for visits in &values.visits {
+        w.write_all(&visits.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_binary(r: &mut impl Read) -> io::Result<(Header, Values)> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a binary values file"));
+    }
+
+    let mut version = [0u8; 2];
+    r.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+    check_version(version)?;
+
+    let mut player = [0u8; 1];
+    r.read_exact(&mut player)?;
+    let indexing = if version > 1 {
+        let mut indexing = [0u8; 1];
+        r.read_exact(&mut indexing)?;
+        indexing_from_byte(indexing[0])?
+    } else {
+        Indexing::Ternary
+    };
+    let mut f32_bytes = [0u8; 4];
+    r.read_exact(&mut f32_bytes)?;
+    let step_size = f32::from_le_bytes(f32_bytes);
+    r.read_exact(&mut f32_bytes)?;
+    let exploration_probability = f32::from_le_bytes(f32_bytes);
+    let mut u64_bytes = [0u8; 8];
+    r.read_exact(&mut u64_bytes)?;
+    let training_games = u64::from_le_bytes(u64_bytes);
+    let header = Header {
+        player: player_from_byte(player[0])?,
+        step_size,
+        exploration_probability,
+        training_games,
+    };
+
+    let mut count = [0u8; 4];
+    r.read_exact(&mut count)?;
+    let count = u32::from_le_bytes(count) as usize;
+    check_count(count, indexing, version)?;
+    let mut estimates = Vec::with_capacity(count);
+    for _ in 0..count {
+        r.read_exact(&mut f32_bytes)?;
+        estimates.push(f32::from_le_bytes(f32_bytes));
+    }
+    let mut visits = Vec::with_capacity(count);
+    if version > 3 {
+        let mut u32_bytes = [0u8; 4];
+        for _ in 0..count {
+            r.read_exact(&mut u32_bytes)?;
+            visits.push(u32::from_le_bytes(u32_bytes));
+        }
+    }
+
+    Ok((header, upgrade(header.player, indexing, estimates, visits)))
+}
+
+fn write_text(
+    w: &mut impl Write,
+    header: &Header,
+    values: &Values,
+) -> io::Result<()> {
+    writeln!(w, "{}", TEXT_MAGIC)?;
+    writeln!(w, "version {}", VERSION)?;
+    writeln!(w, "player {}", player_to_byte(header.player) as char)?;
+    writeln!(w, "indexing {}", indexing_to_byte(values.indexing) as char)?;
+    writeln!(w, "step_size {}", header.step_size)?;
+    writeln!(
+        w,
+        "exploration_probability {}",
+        header.exploration_probability
+    )?;
+    writeln!(w, "training_games {}", header.training_games)?;
+    writeln!(w, "values {}", values.estimates.len())?;
+    for value in &values.estimates {
+        writeln!(w, "{}", value)?;
+    }
+    writeln!(w, "visits {}", values.visits.len())?;
+    for visits in &values.visits {
+        writeln!(w, "{}", visits)?;
+    }
+
+    Ok(())
+}
+
+fn read_text(r: &mut impl BufRead) -> io::Result<(Header, Values)> {
+    let mut lines = r.lines();
+    let mut next_line = || {
+        lines
+            .next()
+            .unwrap_or_else(|| Err(invalid_data("Unexpected end of file")))
+    };
+
+    if next_line()? != TEXT_MAGIC {
+        return Err(invalid_data("Not a text values file"));
+    }
+
+    // Each header line is a key followed by a value. The keys must come in
+    // the same order as they are written in.
+    let mut field = |key: &str| -> io::Result<String> {
+        let line = next_line()?;
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(k), Some(value)) if k == key => Ok(value.trim().to_string()),
+            _ => Err(invalid_data(&format!("Expected the {} field", key))),
+        }
+    };
+
+    let version = parse(&field("version")?)?;
+    check_version(version)?;
+    let first_byte = |s: String| *s.as_bytes().first().unwrap_or(&0);
+    let player = player_from_byte(first_byte(field("player")?))?;
+    let indexing = if version > 1 {
+        indexing_from_byte(first_byte(field("indexing")?))?
+    } else {
+        Indexing::Ternary
+    };
+    let header = Header {
+        player,
+        step_size: parse(&field("step_size")?)?,
+        exploration_probability: parse(&field("exploration_probability")?)?,
+        training_games: parse(&field("training_games")?)?,
+    };
+
+    let count = parse(&field("values")?)?;
+    check_count(count, indexing, version)?;
+    let estimates = (0..count)
+        .map(|_| parse(&next_line()?))
+        .collect::<io::Result<_>>()?;
+    let visits = if version > 3 {
+        if next_line()? != format!("visits {}", count) {
+            return Err(invalid_data("Expected as many visits as values"));
+        }
+        (0..count)
+            .map(|_| parse(&next_line()?))
+            .collect::<io::Result<_>>()?
+    } else {
+        Vec::new()
+    };
+
+    Ok((header, upgrade(header.player, indexing, estimates, visits)))
+}
+
+fn write_network_binary(
+    w: &mut impl Write,
+    header: &Header,
+    network: &Network,
+) -> io::Result<()> {
+    w.write_all(NETWORK_MAGIC)?;
+    w.write_all(&NETWORK_VERSION.to_le_bytes())?;
+    w.write_all(&[player_to_byte(header.player)])?;
+    w.write_all(&header.step_size.to_le_bytes())?;
+    w.write_all(&header.exploration_probability.to_le_bytes())?;
+    w.write_all(&header.training_games.to_le_bytes())?;
+    w.write_all(&(INPUTS as u32).to_le_bytes())?;
+    w.write_all(&(network.hidden as u32).to_le_bytes())?;
+    for weight in &network.weights {
+        w.write_all(&weight.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_network_binary(r: &mut impl Read) -> io::Result<(Header, Network)> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != NETWORK_MAGIC {
+        return Err(invalid_data("Not a binary network file"));
+    }
+
+    let mut version = [0u8; 2];
+    r.read_exact(&mut version)?;
+    check_network_version(u16::from_le_bytes(version))?;
+
+    let mut player = [0u8; 1];
+    r.read_exact(&mut player)?;
+    let mut f32_bytes = [0u8; 4];
+    r.read_exact(&mut f32_bytes)?;
+    let step_size = f32::from_le_bytes(f32_bytes);
+    r.read_exact(&mut f32_bytes)?;
+    let exploration_probability = f32::from_le_bytes(f32_bytes);
+    let mut u64_bytes = [0u8; 8];
+    r.read_exact(&mut u64_bytes)?;
+    let training_games = u64::from_le_bytes(u64_bytes);
+    let header = Header {
+        player: player_from_byte(player[0])?,
+        step_size,
+        exploration_probability,
+        training_games,
+    };
+
+    let mut u32_bytes = [0u8; 4];
+    r.read_exact(&mut u32_bytes)?;
+    check_inputs(u32::from_le_bytes(u32_bytes) as usize)?;
+    r.read_exact(&mut u32_bytes)?;
+    let hidden = u32::from_le_bytes(u32_bytes) as usize;
+    let count = check_hidden(hidden)?;
+    let mut weights = Vec::new();
+    for _ in 0..count {
+        r.read_exact(&mut f32_bytes)?;
+        weights.push(f32::from_le_bytes(f32_bytes));
+    }
+
+    let network = Network {
+        player: header.player,
+        hidden,
+        weights,
+    };
+    Ok((header, network))
+}
+
+fn write_network_text(
+    w: &mut impl Write,
+    header: &Header,
+    network: &Network,
+) -> io::Result<()> {
+    writeln!(w, "{}", NETWORK_TEXT_MAGIC)?;
+    writeln!(w, "version {}", NETWORK_VERSION)?;
+    writeln!(w, "player {}", player_to_byte(header.player) as char)?;
+    writeln!(w, "step_size {}", header.step_size)?;
+    writeln!(
+        w,
+        "exploration_probability {}",
+        header.exploration_probability
+    )?;
+    writeln!(w, "training_games {}", header.training_games)?;
+    writeln!(w, "inputs {}", INPUTS)?;
+    writeln!(w, "hidden {}", network.hidden)?;
+    writeln!(w, "weights {}", network.weights.len())?;
+    for weight in &network.weights {
+        writeln!(w, "{}", weight)?;
+    }
+
+    Ok(())
+}
+
+fn read_network_text(r: &mut impl BufRead) -> io::Result<(Header, Network)> {
+    let mut lines = r.lines();
+    let mut next_line = || {
+        lines
+            .next()
+            .unwrap_or_else(|| Err(invalid_data("Unexpected end of file")))
+    };
+
+    if next_line()? != NETWORK_TEXT_MAGIC {
+        return Err(invalid_data("Not a text network file"));
+    }
+
+    // The keys must come in the same order as they are written in.
+    let mut field = |key: &str| -> io::Result<String> {
+        let line = next_line()?;
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(k), Some(value)) if k == key => Ok(value.trim().to_string()),
+            _ => Err(invalid_data(&format!("Expected the {} field", key))),
+        }
+    };
+
+    check_network_version(parse(&field("version")?)?)?;
+    let player = field("player")?;
+    let header = Header {
+        player: player_from_byte(*player.as_bytes().first().unwrap_or(&0))?,
+        step_size: parse(&field("step_size")?)?,
+        exploration_probability: parse(&field("exploration_probability")?)?,
+        training_games: parse(&field("training_games")?)?,
+    };
+    check_inputs(parse(&field("inputs")?)?)?;
+    let hidden = parse(&field("hidden")?)?;
+    let expected = check_hidden(hidden)?;
+    let count = parse(&field("weights")?)?;
+    if count != expected {
+        return Err(invalid_data(&format!(
+            "Expected {} weights, got {}",
+            expected, count
+        )));
+    }
+    let weights = (0..count)
+        .map(|_| parse(&next_line()?))
+        .collect::<io::Result<_>>()?;
+
+    let network = Network {
+        player: header.player,
+        hidden,
+        weights,
+    };
+    Ok((header, network))
+}
+
+fn player_to_byte(player: Player) -> u8 {
+    match player {
+        Player::X => b'X',
+        Player::O => b'O',
+    }
+}
+
+fn player_from_byte(byte: u8) -> io::Result<Player> {
+    match byte {
+        b'X' => Ok(Player::X),
+        b'O' => Ok(Player::O),
+        _ => Err(invalid_data("Player must be either X or O")),
+    }
+}
+
+fn indexing_to_byte(indexing: Indexing) -> u8 {
+    match indexing {
+        Indexing::Ternary => b't',
+        Indexing::Canonical => b'c',
+        Indexing::Reachable => b'r',
+    }
+}
+
+fn indexing_from_byte(byte: u8) -> io::Result<Indexing> {
+    match byte {
+        b't' => Ok(Indexing::Ternary),
+        b'c' => Ok(Indexing::Canonical),
+        b'r' => Ok(Indexing::Reachable),
+        _ => Err(invalid_data("Indexing must be one of t, c or r")),
+    }
+}
+
+fn check_version(version: u16) -> io::Result<()> {
+    if (1..=VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!("Unsupported version {}", version)))
+    }
+}
+
+fn check_network_version(version: u16) -> io::Result<()> {
+    if version == NETWORK_VERSION {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!("Unsupported version {}", version)))
+    }
+}
+
+/// The network must take the inputs which `network::inputs` gives.
+fn check_inputs(inputs: usize) -> io::Result<()> {
+    if inputs == INPUTS {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!(
+            "Expected a network with {} inputs, got {}",
+            INPUTS, inputs
+        )))
+    }
+}
+
+/// A network must have at least one and at most `MAX_HIDDEN` hidden units.
+/// Gives how many weights it has, like `Network::weight_count` but without
+/// overflowing on whatever number a file holds.
+fn check_hidden(hidden: usize) -> io::Result<usize> {
+    let count = Some(hidden)
+        .filter(|hidden| (1..=MAX_HIDDEN).contains(hidden))
+        .and_then(|hidden| hidden.checked_mul(INPUTS + 2))
+        .and_then(|count| count.checked_add(1));
+    count.ok_or_else(|| {
+        invalid_data(&format!(
+            "Expected between 1 and {} hidden units, got {}",
+            MAX_HIDDEN, hidden
+        ))
+    })
+}
+
+/// There must be exactly one value per position the indexing had in given
+/// version.
+fn check_count(
+    count: usize,
+    indexing: Indexing,
+    version: u16,
+) -> io::Result<()> {
+    let expected = match indexing {
+        Indexing::Reachable if version < 3 => ReachableGrids::get().x_first,
+        indexing => indexing.len(),
+    };
+    if count == expected {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!(
+            "Expected {} values, got {}",
+            expected, count
+        )))
+    }
+}
+
+/// Gives the positions which older versions didn't have their initial values
+/// and no visits.
+fn upgrade(
+    player: Player,
+    indexing: Indexing,
+    estimates: Vec<f32>,
+    visits: Vec<u32>,
+) -> Values {
+    let mut values = initial_values(player, indexing);
+    values.estimates[..estimates.len()].copy_from_slice(&estimates);
+    values.visits[..visits.len()].copy_from_slice(&visits);
+    values
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> io::Result<T> {
+    s.trim()
+        .parse()
+        .map_err(|_| invalid_data(&format!("Cannot parse '{}'", s)))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeededRng;
+    use rand::SeedableRng;
+    use std::io::Cursor;
+
+    fn header() -> Header {
+        Header {
+            player: Player::O,
+            step_size: 0.25,
+            exploration_probability: 0.125,
+            training_games: 12345,
+        }
+    }
+
+    /// Values which differ from the initial ones at every position, and
+    /// whose visits do too.
+    fn values(indexing: Indexing) -> Values {
+        let mut values = initial_values(Player::O, indexing);
+        for (i, (value, visits)) in values
+            .estimates
+            .iter_mut()
+            .zip(&mut values.visits)
+            .enumerate()
+        {
+            *value = (i % 97) as f32 / 97.0;
+            *visits = i as u32;
+        }
+        values
+    }
+
+    type Loaded<T> = io::Result<(Header, T)>;
+
+    /// Saves and loads one kind of model in one format.
+    struct Format<T> {
+        write: fn(&mut Vec<u8>, &Header, &T) -> io::Result<()>,
+        read: fn(&mut Cursor<Vec<u8>>) -> Loaded<T>,
+        text: bool,
+    }
+
+    impl<T> Format<T> {
+        fn save(&self, model: &T) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            (self.write)(&mut bytes, &header(), model).unwrap();
+            bytes
+        }
+
+        fn load(&self, bytes: Vec<u8>) -> Loaded<T> {
+            (self.read)(&mut Cursor::new(bytes))
+        }
+    }
+
+    fn value_formats() -> [Format<Values>; 2] {
+        [
+            Format {
+                write: write_binary,
+                read: read_binary,
+                text: false,
+            },
+            Format {
+                write: write_text,
+                read: read_text,
+                text: true,
+            },
+        ]
+    }
+
+    fn network_formats() -> [Format<Network>; 2] {
+        [
+            Format {
+                write: write_network_binary,
+                read: read_network_binary,
+                text: false,
+            },
+            Format {
+                write: write_network_text,
+                read: read_network_text,
+                text: true,
+            },
+        ]
+    }
+
+    fn text(values: &Values) -> String {
+        String::from_utf8(value_formats()[1].save(values)).unwrap()
+    }
+
+    /// Checks what every format must do: it loads the model it saved, and it
+    /// rejects the file when its last value is cut off or its magic is wrong.
+    fn assert_format<T>(
+        format: &Format<T>,
+        model: &T,
+        assert_same: impl Fn(&T, &T),
+    ) {
+        let bytes = format.save(model);
+        let (header, loaded) = format.load(bytes.clone()).unwrap();
+        assert_same_header(&header);
+        assert_same(&loaded, model);
+
+        // A binary file ends early, a text file has a line too few.
+        let mut truncated = bytes.clone();
+        let expected = if format.text {
+            let last_line = truncated[..truncated.len() - 1]
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .unwrap();
+            truncated.truncate(last_line);
+            io::ErrorKind::InvalidData
+        } else {
+            truncated.pop();
+            io::ErrorKind::UnexpectedEof
+        };
+        match format.load(truncated) {
+            Err(error) => assert_eq!(error.kind(), expected),
+            Ok(_) => panic!("Expected the truncated file to be rejected"),
+        }
+
+        let mut bad_magic = bytes;
+        bad_magic[0] = b'X';
+        assert_invalid_data(format.load(bad_magic));
+    }
+
+    /// Loads the binary file, saves it as text, loads that and saves it as
+    /// binary again, which must give the same bytes.
+    fn assert_binary_through_text_is_identical<T>(
+        [binary, text]: &[Format<T>; 2],
+        model: &T,
+    ) {
+        let bytes = binary.save(model);
+        let (header, model) = binary.load(bytes.clone()).unwrap();
+        let mut as_text = Vec::new();
+        (text.write)(&mut as_text, &header, &model).unwrap();
+        let (header, model) = text.load(as_text).unwrap();
+        let mut again = Vec::new();
+        (binary.write)(&mut again, &header, &model).unwrap();
+        assert_eq!(again, bytes);
+    }
+
+    /// A binary file the way an older version wrote it: no indexing before
+    /// version 2 and no visits before version 4.
+    fn old_binary(
+        version: u16,
+        indexing: Indexing,
+        estimates: &[f32],
+    ) -> Vec<u8> {
+        let header = header();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.push(player_to_byte(header.player));
+        if version > 1 {
+            bytes.push(indexing_to_byte(indexing));
+        }
+        bytes.extend_from_slice(&header.step_size.to_le_bytes());
+        bytes.extend_from_slice(&header.exploration_probability.to_le_bytes());
+        bytes.extend_from_slice(&header.training_games.to_le_bytes());
+        bytes.extend_from_slice(&(estimates.len() as u32).to_le_bytes());
+        for value in estimates {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// A text file the way an older version wrote it.
+    fn old_text(version: u16, indexing: Indexing, estimates: &[f32]) -> String {
+        let header = header();
+        let mut text =
+            format!("{}\nversion {}\nplayer O\n", TEXT_MAGIC, version);
+        if version > 1 {
+            text +=
+                &format!("indexing {}\n", indexing_to_byte(indexing) as char);
+        }
+        text += &format!(
+            "step_size {}\nexploration_probability {}\ntraining_games {}\n",
+            header.step_size,
+            header.exploration_probability,
+            header.training_games
+        );
+        text += &format!("values {}\n", estimates.len());
+        for value in estimates {
+            text += &format!("{}\n", value);
+        }
+        text
+    }
+
+    /// Loads the old binary and text files and checks that the values in
+    /// them are kept, the positions they lack have their initial values and
+    /// there are no visits.
+    fn assert_upgrades(version: u16, indexing: Indexing, count: usize) {
+        let estimates = &values(indexing).estimates[..count];
+        let bytes = old_binary(version, indexing, estimates);
+        let text = old_text(version, indexing, estimates);
+        let loaded = [
+            read_binary(&mut Cursor::new(bytes)).unwrap(),
+            read_text(&mut Cursor::new(text)).unwrap(),
+        ];
+        let initial = initial_values(Player::O, indexing);
+        for (header, values) in &loaded {
+            assert_same_header(header);
+            assert_eq!(values.indexing, indexing);
+            assert_eq!(&values.estimates[..count], estimates);
+            assert_eq!(values.estimates[count..], initial.estimates[count..]);
+            assert!(values.visits.iter().all(|visits| *visits == 0));
+        }
+    }
+
+    #[test]
+    fn values_round_trip_and_damaged_files_are_rejected() {
+        for indexing in
+            [Indexing::Ternary, Indexing::Canonical, Indexing::Reachable]
+        {
+            for format in &value_formats() {
+                assert_format(format, &values(indexing), |loaded, values| {
+                    assert_eq!(loaded.indexing, values.indexing);
+                    assert_eq!(loaded.estimates, values.estimates);
+                    assert_eq!(loaded.visits, values.visits);
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn values_binary_through_text_is_identical() {
+        assert_binary_through_text_is_identical(
+            &value_formats(),
+            &values(Indexing::Reachable),
+        );
+    }
+
+    #[test]
+    fn version_1_is_ternary_without_visits() {
+        assert_upgrades(1, Indexing::Ternary, 3usize.pow(9));
+    }
+
+    #[test]
+    fn version_2_lacks_grids_only_reachable_when_o_moves_first() {
+        let x_first = ReachableGrids::get().x_first;
+        assert!(x_first < Indexing::Reachable.len());
+        assert_upgrades(2, Indexing::Reachable, x_first);
+        assert_upgrades(2, Indexing::Canonical, 3usize.pow(9));
+    }
+
+    #[test]
+    fn version_3_has_no_visits() {
+        assert_upgrades(3, Indexing::Reachable, Indexing::Reachable.len());
+    }
+
+    #[test]
+    fn rejects_wrong_count() {
+        // One value too few in the current version, one too many in the
+        // previous one.
+        let estimates = &values(Indexing::Ternary).estimates;
+        let count = Indexing::Reachable.len();
+        let bytes = old_binary(4, Indexing::Reachable, &estimates[..count - 1]);
+        assert_invalid_data(read_binary(&mut Cursor::new(bytes)));
+        let text = old_text(3, Indexing::Reachable, &estimates[..count + 1]);
+        assert_invalid_data(read_text(&mut Cursor::new(text)));
+    }
+
+    #[test]
+    fn rejects_fewer_visits_than_values() {
+        let values = values(Indexing::Reachable);
+        let count = values.estimates.len();
+        let text = text(&values).replace(
+            &format!("visits {}", count),
+            &format!("visits {}", count - 1),
+        );
+        assert_invalid_data(read_text(&mut Cursor::new(text)));
+    }
+
+    fn network() -> Network {
+        Network::new(&mut SeededRng::seed_from_u64(0), Player::O, 3)
+    }
+
+    fn assert_same_header(header: &Header) {
+        let expected = self::header();
+        assert_eq!(header.player, expected.player);
+        assert_eq!(header.step_size, expected.step_size);
+        assert_eq!(
+            header.exploration_probability,
+            expected.exploration_probability
+        );
+        assert_eq!(header.training_games, expected.training_games);
+    }
+
+    fn assert_invalid_data<T>(result: io::Result<T>) {
+        match result {
+            Err(error) => assert_eq!(error.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("Expected the file to be rejected"),
+        }
+    }
+
+    #[test]
+    fn networks_round_trip_and_damaged_files_are_rejected() {
+        for format in &network_formats() {
+            assert_format(format, &network(), |loaded, network| {
+                assert_eq!(loaded.player, network.player);
+                assert_eq!(loaded.hidden, network.hidden);
+                assert_eq!(loaded.weights, network.weights);
+            });
+        }
+    }
+
+    #[test]
+    fn network_binary_through_text_is_identical() {
+        assert_binary_through_text_is_identical(&network_formats(), &network());
+    }
+
+    #[test]
+    fn network_rejects_wrong_count() {
+        let count = Network::weight_count(3);
+        let text = String::from_utf8(network_formats()[1].save(&network()))
+            .unwrap()
+            .replace(
+                &format!("weights {}", count),
+                &format!("weights {}", count - 1),
+            );
+        assert_invalid_data(read_network_text(&mut Cursor::new(text)));
+    }
+
+    #[test]
+    fn network_rejects_hidden_out_of_bounds() {
+        // The hidden units are the last field of the header.
+        let [binary, text] = network_formats();
+        let weights = Network::weight_count(3) * 4;
+        let hidden_at = binary.save(&network()).len() - weights - 4;
+        for hidden in [0, MAX_HIDDEN as u32 + 1, u32::MAX] {
+            let mut bytes = binary.save(&network());
+            bytes[hidden_at..hidden_at + 4]
+                .copy_from_slice(&hidden.to_le_bytes());
+            assert_invalid_data(binary.load(bytes));
+        }
+        for hidden in ["0", "65537", "18446744073709551615"] {
+            let saved = String::from_utf8(text.save(&network())).unwrap();
+            let saved =
+                saved.replace("hidden 3", &format!("hidden {}", hidden));
+            assert_invalid_data(text.load(saved.into_bytes()));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! The grid can be rotated and reflected in 8 ways without changing the game.
+//! Grids which can be transformed into one another are represented by the one
+//! with the lowest ordinal, which is called the canonical grid.
+
+use super::Grid;
+
+/// One of the 8 symmetries of the square grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    /// Leaves the grid as it is.
+    Identity,
+    /// Clockwise by 90 degrees.
+    Rotate90,
+    /// By 180 degrees.
+    Rotate180,
+    /// Clockwise by 270 degrees.
+    Rotate270,
+    /// Swaps the left and the right column.
+    FlipHorizontal,
+    /// Swaps the top and the bottom row.
+    FlipVertical,
+    /// Mirrors along the diagonal from the top left corner.
+    Transpose,
+    /// Mirrors along the diagonal from the top right corner.
+    AntiTranspose,
+}
+
+impl Transform {
+    /// All the symmetries, starting with the identity.
+    pub const ALL: [Self; 8] = [
+        Self::Identity,
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+        Self::Transpose,
+        Self::AntiTranspose,
+    ];
+
+    /// Where does the field with given index end up after the transform.
+    /// ```text
+    ///  0 | 1 | 2
+    /// ---+---+---
+    ///  3 | 4 | 5
+    /// ---+---+---
+    ///  6 | 7 | 8
+    /// ```
+    pub fn map_field(self, field_index: usize) -> usize {
+        let map = match self {
+            Self::Identity => [0, 1, 2, 3, 4, 5, 6, 7, 8],
+            Self::Rotate90 => [2, 5, 8, 1, 4, 7, 0, 3, 6],
+            Self::Rotate180 => [8, 7, 6, 5, 4, 3, 2, 1, 0],
+            Self::Rotate270 => [6, 3, 0, 7, 4, 1, 8, 5, 2],
+            Self::FlipHorizontal => [2, 1, 0, 5, 4, 3, 8, 7, 6],
+            Self::FlipVertical => [6, 7, 8, 3, 4, 5, 0, 1, 2],
+            Self::Transpose => [0, 3, 6, 1, 4, 7, 2, 5, 8],
+            Self::AntiTranspose => [8, 5, 2, 7, 4, 1, 6, 3, 0],
+        };
+        map[field_index]
+    }
+
+    /// The transform which undoes this one.
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Rotate90 => Self::Rotate270,
+            Self::Rotate270 => Self::Rotate90,
+            // All the other transforms undo themselves.
+            other => other,
+        }
+    }
+
+    /// Moves each field of the grid to where the transform takes it.
+    pub fn apply(self, grid: Grid) -> Grid {
+        // Moves each set bit of the masks to where its field ends up.
+        let map = |mut marks: u16| {
+            let mut transformed = 0;
+            while marks != 0 {
+                let field = marks.trailing_zeros() as usize;
+                transformed |= 1 << self.map_field(field);
+                marks &= marks - 1;
+            }
+            transformed
+        };
+        Grid {
+            x: map(grid.x),
+            o: map(grid.o),
+        }
+    }
+}
+
+impl Grid {
+    /// Finds the canonical grid among all the symmetries of this grid. Returns
+    /// it together with the transform which turns this grid into it.
+    pub fn canonical(self) -> (Self, Transform) {
+        Transform::ALL
+            .iter()
+            .map(|transform| (transform.apply(self), *transform))
+            .min_by_key(|(grid, _)| grid.to_base_10())
+            .expect("There are always 8 transforms")
+    }
+
+    /// The ordinal of the canonical grid. All symmetrical grids share it.
+    pub fn canonical_base_10(self) -> usize {
+        self.canonical().0.to_base_10()
+    }
+}
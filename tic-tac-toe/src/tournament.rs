@@ -0,0 +1,358 @@
+//! Round-robin tournaments. Every pair of entrants plays the same number of
+//! games with each entrant on each side. The results are shown in a crosstable
+//! and summarized by Elo ratings.
+
+use super::policies::Policy;
+use super::record::{play_sampled, Recorder};
+use super::{seats, Player, SeededRng};
+use std::fmt::Write;
+use std::io;
+
+/// The ratings are anchored to an imaginary entrant of this rating.
+const BASE_RATING: f64 = 1500.0;
+
+/// Each entrant is also considered to have drawn this many games against the
+/// imaginary entrant. Without it, the rating of an entrant which never lost
+/// would grow without bounds.
+const PRIOR_DRAWS: f64 = 2.0;
+
+/// How many times are the ratings refined.
+const RATING_ITERATIONS: usize = 1000;
+
+/// How the games of one entrant against another ended, from the perspective
+/// of the first one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Record {
+    /// Games the first entrant won.
+    pub wins: usize,
+    /// Games which ended in a draw.
+    pub draws: usize,
+    /// Games the first entrant lost.
+    pub losses: usize,
+}
+
+/// The results of a tournament.
+pub struct Standings {
+    /// The names of the entrants, in the order they were given.
+    pub names: Vec<String>,
+    /// The record of each entrant against each other entrant.
+    pub records: Vec<Vec<Record>>,
+    /// The Elo rating of each entrant.
+    pub ratings: Vec<f64>,
+}
+
+impl Record {
+    /// How many games were played.
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Wins count as one point and draws as half a point.
+    pub fn score(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+/// Plays every pair of the entrants for given number of games with each of
+/// them on each side. The recorder records all the games, which fails if the
+/// record can't be written.
+pub fn play(
+    rng: &mut SeededRng,
+    entrants: &mut [(String, Box<dyn Policy + '_>)],
+    games: usize,
+    first: Player,
+    mut recorder: Option<&mut Recorder>,
+) -> io::Result<Standings> {
+    let count = entrants.len();
+    let mut records = vec![vec![Record::default(); count]; count];
+    let pairs = (0..count).flat_map(|a| (a + 1..count).map(move |b| (a, b)));
+    let mut game = 0;
+    for (a, b) in pairs {
+        let (left, right) = entrants.split_at_mut(b);
+        let ((a_name, a_policy), (b_name, b_policy)) =
+            (&mut left[a], &mut right[0]);
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.start("tournament", a_name, b_name);
+        }
+        let mut record = Record::default();
+        for side in &[Player::X, Player::O] {
+            for _ in 0..games {
+                game += 1;
+                let grid = play_sampled(
+                    rng,
+                    seats(*side, a_policy, b_policy),
+                    *side,
+                    first,
+                    game,
+                    recorder.as_deref_mut(),
+                )?;
+                match grid.outcome().winner() {
+                    Some(winner) if winner == *side => record.wins += 1,
+                    Some(_) => record.losses += 1,
+                    None => record.draws += 1,
+                }
+            }
+        }
+        records[a][b] = record;
+        records[b][a] = Record {
+            wins: record.losses,
+            draws: record.draws,
+            losses: record.wins,
+        };
+    }
+
+    Ok(Standings {
+        names: entrants.iter().map(|(name, _)| name.clone()).collect(),
+        ratings: ratings(&records),
+        records,
+    })
+}
+
+/// Fits Elo ratings to all the games at once rather than updating them game
+/// by game, so that they don't depend on the order of the games. Under the
+/// Bradley-Terry model, an entrant of strength `a` scores against an entrant
+/// of strength `b` with probability `a / (a + b)`. The strengths which best
+/// explain the scores are found by the minorization-maximization algorithm,
+/// with draws counting as half a win and half a loss.
+fn ratings(records: &[Vec<Record>]) -> Vec<f64> {
+    // The prior draws are played against an entrant of strength 1.
+    let mut strengths = vec![1.0; records.len()];
+    for _ in 0..RATING_ITERATIONS {
+        strengths = (0..records.len())
+            .map(|i| {
+                let score: f64 = records[i].iter().map(Record::score).sum();
+                let expected: f64 = records[i]
+                    .iter()
+                    .zip(&strengths)
+                    .filter(|(record, _)| record.games() > 0)
+                    .map(|(record, strength)| {
+                        record.games() as f64 / (strengths[i] + strength)
+                    })
+                    .sum();
+                (score + PRIOR_DRAWS / 2.0)
+                    / (expected + PRIOR_DRAWS / (strengths[i] + 1.0))
+            })
+            .collect();
+    }
+
+    strengths
+        .into_iter()
+        .map(|strength| BASE_RATING + 400.0 * strength.log10())
+        .collect()
+}
+
+impl Standings {
+    /// The record of each entrant against all the others.
+    pub fn totals(&self) -> Vec<Record> {
+        self.records
+            .iter()
+            .map(|row| {
+                let mut total = Record::default();
+                row.iter().for_each(|record| total.add(*record));
+                total
+            })
+            .collect()
+    }
+
+    /// The crosstable with a `wins-draws-losses` cell for each entrant in a
+    /// row against each entrant in a column, followed by the entrants ranked
+    /// by their ratings.
+    pub fn to_text(&self) -> String {
+        let cell = |record: &Record| {
+            format!("{}-{}-{}", record.wins, record.draws, record.losses)
+        };
+        let name_width = self
+            .names
+            .iter()
+            .map(String::len)
+            .chain(Some("entrant".len()))
+            .max()
+            .unwrap_or(0);
+        let widths: Vec<_> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(j, name)| {
+                self.records
+                    .iter()
+                    .map(|row| cell(&row[j]).len())
+                    .chain(Some(name.len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut text = String::new();
+        write!(text, "{:name_width$}", "").unwrap();
+        for (name, width) in self.names.iter().zip(&widths) {
+            write!(text, "  {:>width$}", name).unwrap();
+        }
+        writeln!(text).unwrap();
+        for (i, row) in self.records.iter().enumerate() {
+            write!(text, "{:name_width$}", self.names[i]).unwrap();
+            for (j, (record, width)) in row.iter().zip(&widths).enumerate() {
+                let cell = if i == j {
+                    "-".to_string()
+                } else {
+                    cell(record)
+                };
+                write!(text, "  {:>width$}", cell).unwrap();
+            }
+            writeln!(text).unwrap();
+        }
+
+        let totals = self.totals();
+        let mut ranking: Vec<_> = (0..self.names.len()).collect();
+        ranking.sort_by(|a, b| self.ratings[*b].total_cmp(&self.ratings[*a]));
+        writeln!(
+            text,
+            "\nrank  {:name_width$}     elo   wins  draws  losses  score",
+            "entrant"
+        )
+        .unwrap();
+        for (rank, i) in ranking.into_iter().enumerate() {
+            let total = totals[i];
+            writeln!(
+                text,
+                "{:>4}  {:name_width$}  {:>6.0}  {:>5}  {:>5}  {:>6}  {:>4.1}%",
+                rank + 1,
+                self.names[i],
+                self.ratings[i],
+                total.wins,
+                total.draws,
+                total.losses,
+                100.0 * total.score() / total.games().max(1) as f64
+            )
+            .unwrap();
+        }
+        text
+    }
+
+    /// One row per entrant with its rating, its total record and its record
+    /// against each entrant as `wins-draws-losses`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("entrant,elo,wins,draws,losses");
+        for name in &self.names {
+            write!(csv, ",{}", csv_field(name)).unwrap();
+        }
+        writeln!(csv).unwrap();
+
+        let totals = self.totals();
+        for (i, row) in self.records.iter().enumerate() {
+            let total = totals[i];
+            write!(
+                csv,
+                "{},{:.1},{},{},{}",
+                csv_field(&self.names[i]),
+                self.ratings[i],
+                total.wins,
+                total.draws,
+                total.losses
+            )
+            .unwrap();
+            for (j, record) in row.iter().enumerate() {
+                if i == j {
+                    write!(csv, ",-").unwrap();
+                } else {
+                    write!(
+                        csv,
+                        ",{}-{}-{}",
+                        record.wins, record.draws, record.losses
+                    )
+                    .unwrap();
+                }
+            }
+            writeln!(csv).unwrap();
+        }
+        csv
+    }
+}
+
+/// Quotes the field if it contains a character which has a meaning in CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(wins: usize, draws: usize, losses: usize) -> Record {
+        Record {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn assert_ratings(records: &[Vec<Record>], expected: &[f64]) {
+        let ratings = ratings(records);
+        assert_eq!(ratings.len(), expected.len());
+        for (rating, expected) in ratings.iter().zip(expected) {
+            assert!(
+                (rating - expected).abs() < 1e-6,
+                "{:?} != {:?}",
+                ratings,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn symmetric_results_give_equal_ratings() {
+        let none = Record::default();
+        assert_ratings(
+            &[vec![none, record(5, 0, 5)], vec![record(5, 0, 5), none]],
+            &[BASE_RATING; 2],
+        );
+        assert_ratings(
+            &[
+                vec![none, record(0, 4, 0), record(0, 4, 0)],
+                vec![record(0, 4, 0), none, record(0, 4, 0)],
+                vec![record(0, 4, 0), record(0, 4, 0), none],
+            ],
+            &[BASE_RATING; 3],
+        );
+    }
+
+    #[test]
+    fn clean_sweep_is_bounded_by_the_prior_draws() {
+        // With the strengths 3 and 1/3, the winner scores 5 points and a
+        // half of the 2 prior draws, 6 in total, and is expected to score
+        // 5 * 3 / (3 + 1/3) + 2 * 3 / (3 + 1) = 6. The loser scores 1 and is
+        // expected to score 5 / 10 + 2 / 4 = 1.
+        let none = Record::default();
+        let difference = 400.0 * 3f64.log10();
+        assert_ratings(
+            &[vec![none, record(5, 0, 0)], vec![record(0, 0, 5), none]],
+            &[BASE_RATING + difference, BASE_RATING - difference],
+        );
+    }
+
+    #[test]
+    fn entrant_without_games_keeps_the_base_rating() {
+        let none = Record::default();
+        let pair = ratings(&[
+            vec![none, record(3, 1, 0)],
+            vec![record(0, 1, 3), none],
+        ]);
+        assert_ratings(
+            &[
+                vec![none, record(3, 1, 0), none],
+                vec![record(0, 1, 3), none, none],
+                vec![none, none, none],
+            ],
+            &[pair[0], pair[1], BASE_RATING],
+        );
+    }
+}
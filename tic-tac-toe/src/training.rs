@@ -0,0 +1,425 @@
+//! Trains learners against opponents or against each other, and compares how
+//! fast the learners learn.
+
+use super::evaluation;
+use super::learner::{Algorithm, Hyperparameters, Learner};
+use super::monte_carlo::Visits;
+use super::policies::{Policy, Random};
+use super::record::{play_sampled, Recorder};
+use super::{
+    play_game, seats, GameOutcome, Grid, Indexing, Player, SeededRng, Values,
+};
+use rand::prelude::*;
+use std::fmt;
+use std::io;
+
+/// How many times is the progress reported during self-play.
+const REPORTS: usize = 10;
+
+/// Counts how the games ended.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tally {
+    /// Games won by X.
+    pub x_wins: usize,
+    /// Games won by O.
+    pub o_wins: usize,
+    /// Games which ended in a draw.
+    pub draws: usize,
+}
+
+impl Tally {
+    /// Records the outcome of a game given its final grid.
+    pub fn record(&mut self, grid: Grid) {
+        match grid.outcome() {
+            GameOutcome::XWins => self.x_wins += 1,
+            GameOutcome::OWins => self.o_wins += 1,
+            GameOutcome::Draw => self.draws += 1,
+            GameOutcome::Ongoing => panic!("The game must be over"),
+        }
+    }
+
+    /// How many games were recorded.
+    pub fn games(&self) -> usize {
+        self.x_wins + self.o_wins + self.draws
+    }
+
+    /// How many games did given player win.
+    pub fn wins(&self, player: Player) -> usize {
+        match player {
+            Player::X => self.x_wins,
+            Player::O => self.o_wins,
+        }
+    }
+
+    /// How many games did given player lose.
+    pub fn losses(&self, player: Player) -> usize {
+        self.wins(player.opponent())
+    }
+}
+
+impl fmt::Display for Tally {
+    /// Prints the rates from the perspective of X, i.e. O wins are X losses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |count| 100.0 * count as f32 / self.games().max(1) as f32;
+        write!(
+            f,
+            "X wins {:5.1}%  draws {:5.1}%  X losses {:5.1}%",
+            rate(self.x_wins),
+            rate(self.draws),
+            rate(self.o_wins),
+        )
+    }
+}
+
+/// Which side the learner plays and who moves first in each training game.
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    /// The side the learner plays, unless it alternates.
+    pub side: Player,
+    /// Who moves first, unless it alternates.
+    pub first: Player,
+    /// Cycles through both sides and both move orders instead, so that the
+    /// learner gets good at all of them.
+    pub alternate: bool,
+}
+
+impl Schedule {
+    /// The side of the learner and the player who moves first in given game.
+    pub fn game(&self, game: usize) -> (Player, Player) {
+        if !self.alternate {
+            return (self.side, self.first);
+        }
+
+        // The side changes every game and the first mover every other game.
+        let swap = |player: Player, swap| {
+            if swap {
+                player.opponent()
+            } else {
+                player
+            }
+        };
+        (
+            swap(self.side, game % 2 == 1),
+            swap(self.first, game / 2 % 2 == 1),
+        )
+    }
+}
+
+/// Trains the learner by letting it play against the opponent, with sides and
+/// move orders given by the schedule. The recorder records a sample of the
+/// games, which fails if the record can't be written.
+pub fn train(
+    rng: &mut SeededRng,
+    learner: &mut dyn Policy,
+    opponent: &mut dyn Policy,
+    games: usize,
+    schedule: Schedule,
+    mut recorder: Option<&mut Recorder>,
+) -> io::Result<()> {
+    for game in 0..games {
+        let (side, first) = schedule.game(game);
+        play_sampled(
+            rng,
+            seats(side, learner, opponent),
+            side,
+            first,
+            game as u64 + 1,
+            recorder.as_deref_mut(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Trains two learners by letting them play against each other. Each learner
+/// keeps its own set of values which are updated at the same time. The
+/// schedule says which side the first learner plays. Every tenth of the
+/// games, it prints the rates with which X won, drew and lost since the
+/// previous report. The recorder records a sample of the games, which fails
+/// if the record can't be written.
+pub fn self_play(
+    rng: &mut SeededRng,
+    learner: &mut dyn Policy,
+    opponent: &mut dyn Policy,
+    games: usize,
+    schedule: Schedule,
+    mut recorder: Option<&mut Recorder>,
+) -> io::Result<Tally> {
+    let report_every = (games / REPORTS).max(1);
+    let mut total = Tally::default();
+    let mut window = Tally::default();
+    for game in 1..=games {
+        let (side, first) = schedule.game(game - 1);
+        let grid = play_sampled(
+            rng,
+            seats(side, learner, opponent),
+            side,
+            first,
+            game as u64,
+            recorder.as_deref_mut(),
+        )?;
+        total.record(grid);
+        window.record(grid);
+
+        if game % report_every == 0 || game == games {
+            println!("games {:>8}: {}", game, window);
+            window = Tally::default();
+        }
+    }
+
+    Ok(total)
+}
+
+/// Prints for each number of marks on the grid how many grids the learner
+/// visited and how often, so that it's visible which positions it barely
+/// knows. Grids which were never visited are left out.
+pub fn report_visits(values: &Values) {
+    let mut by_marks = vec![Vec::new(); 10];
+    for (index, visits) in values.visits.iter().enumerate() {
+        if *visits > 0 {
+            let grid = values.indexing.grid(index);
+            let marks = 9 - grid.empty_fields().len();
+            by_marks[marks].push(*visits);
+        }
+    }
+
+    println!("marks   grids  fewest visits  median visits  most visits");
+    for (marks, visits) in by_marks.iter_mut().enumerate() {
+        if visits.is_empty() {
+            continue;
+        }
+        visits.sort_unstable();
+        println!(
+            "{:>5} {:>7} {:>14} {:>14} {:>12}",
+            marks,
+            visits.len(),
+            visits[0],
+            visits[visits.len() / 2],
+            visits[visits.len() - 1]
+        );
+    }
+}
+
+/// The loss rate against a random policy under which the agent is considered
+/// to have converged.
+const CONVERGED_LOSS_RATE: f32 = 0.01;
+
+/// The loss rate is noisy, therefore it must stay under `CONVERGED_LOSS_RATE`
+/// for this many checkpoints in a row.
+const CONVERGED_CHECKPOINTS: usize = 5;
+
+/// Trains a learner with ternary values and a learner with canonical values
+/// side by side against a random policy and prints how much faster the
+/// canonical values converged, i.e. the speedup gained from the symmetries.
+pub fn compare_symmetry(seed: u64, games: usize) {
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let mut learners = [
+        (
+            "ternary",
+            Algorithm::Afterstate.learner(
+                &mut rng,
+                Player::X,
+                Indexing::Ternary,
+                Hyperparameters::default(),
+            ),
+        ),
+        (
+            "canonical",
+            Algorithm::Afterstate.learner(
+                &mut rng,
+                Player::X,
+                Indexing::Canonical,
+                Hyperparameters::default(),
+            ),
+        ),
+    ];
+
+    if let [Some(ternary), Some(canonical)] =
+        compare(seed, &mut learners, games).0[..]
+    {
+        println!(
+            "Canonical values converged {:.1}x faster.",
+            ternary as f32 / canonical as f32
+        );
+    }
+}
+
+/// Trains a learner of each algorithm side by side against a random policy
+/// and compares how fast they converge and how well they play in the end.
+pub fn compare_algorithms(
+    seed: u64,
+    games: usize,
+    indexing: Indexing,
+    hyperparameters: Hyperparameters,
+) {
+    // The initial weights are drawn from a generator of their own, so that
+    // the games of each learner don't depend on which learners come before.
+    let mut rng = SeededRng::seed_from_u64(seed);
+    let mut learners = [
+        (
+            "afterstate",
+            Algorithm::Afterstate.learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "q-learning",
+            Algorithm::QLearning.learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "sarsa",
+            Algorithm::Sarsa.learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "mc-first",
+            Algorithm::MonteCarlo(Visits::First).learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "mc-every",
+            Algorithm::MonteCarlo(Visits::Every).learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "linear",
+            Algorithm::Linear.learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+        (
+            "network",
+            Algorithm::Network.learner(
+                &mut rng,
+                Player::X,
+                indexing,
+                hyperparameters,
+            ),
+        ),
+    ];
+
+    let (_, mut rngs) = compare(seed, &mut learners, games);
+
+    for ((name, learner), rng) in learners.iter().zip(&mut rngs) {
+        println!("\n{}:", name);
+        evaluation::against_minimax(
+            rng,
+            &mut learner.greedy(),
+            Player::X,
+            Player::X,
+        );
+    }
+}
+
+/// Trains the learners side by side against a random policy. All of them play
+/// X. Every so often, the exact rate with which they would lose playing
+/// greedily against a random policy is computed. Each learner draws from its
+/// own generator with the same seed, so that none of them changes the games
+/// of the others. Returns for each learner how many games it needed to bring
+/// its loss rate under `CONVERGED_LOSS_RATE` for good, and the generators.
+fn compare(
+    seed: u64,
+    learners: &mut [(&str, Box<dyn Learner>)],
+    games: usize,
+) -> (Vec<Option<usize>>, Vec<SeededRng>) {
+    let mut rngs: Vec<_> = learners
+        .iter()
+        .map(|_| SeededRng::seed_from_u64(seed))
+        .collect();
+    // When did the loss rate get under the threshold and for how many
+    // checkpoints in a row it stayed there.
+    let mut converged = vec![(None, 0); learners.len()];
+
+    let checkpoint_every = (games / (REPORTS * 20)).max(1);
+    print!("   games");
+    for (name, _) in learners.iter() {
+        print!(" {:>12}", name);
+    }
+    println!("\n{:>8} {:>12}", "", "loss rates");
+
+    for game in 1..=games {
+        for ((_, learner), rng) in learners.iter_mut().zip(&mut rngs) {
+            play_game(rng, learner, &mut Random, Player::X);
+        }
+
+        if game % checkpoint_every != 0 {
+            continue;
+        }
+
+        let loss_rates: Vec<_> = learners
+            .iter()
+            .zip(&mut rngs)
+            .map(|((_, learner), rng)| {
+                evaluation::against_random(
+                    rng,
+                    &mut learner.greedy(),
+                    Player::X,
+                    Player::X,
+                )
+                .loss
+            })
+            .collect();
+        for ((since, streak), loss_rate) in
+            converged.iter_mut().zip(&loss_rates)
+        {
+            if *streak >= CONVERGED_CHECKPOINTS {
+                continue;
+            } else if *loss_rate < CONVERGED_LOSS_RATE {
+                *since = since.or(Some(game));
+                *streak += 1;
+            } else {
+                *since = None;
+                *streak = 0;
+            }
+        }
+
+        if game % (checkpoint_every * 20) == 0 {
+            print!("{:>8}", game);
+            for loss_rate in loss_rates {
+                print!(" {:>11.2}%", 100.0 * loss_rate);
+            }
+            println!();
+        }
+    }
+
+    println!(
+        "Games until the loss rate stayed under {}%:",
+        100.0 * CONVERGED_LOSS_RATE
+    );
+    let converged: Vec<_> = converged
+        .into_iter()
+        .map(|(since, streak)| {
+            Some(since?).filter(|_| streak >= CONVERGED_CHECKPOINTS)
+        })
+        .collect();
+    for ((name, _), games) in learners.iter().zip(&converged) {
+        match games {
+            Some(games) => println!("{:>12} {}", name, games),
+            None => println!("{:>12} did not converge", name),
+        }
+    }
+
+    (converged, rngs)
+}